@@ -55,7 +55,7 @@ impl Handle {
 }
 
 pub async fn check_auth(url: &str, auth: &Handle, required: bool) -> josh::JoshResult<bool> {
-    if required && auth.hash == "" {
+    if required && auth.hash.is_empty() {
         return Ok(false);
     }
 
@@ -75,7 +75,7 @@ pub async fn check_auth(url: &str, auth: &Handle, required: bool) -> josh::JoshR
 
     let password = AUTH
         .lock()?
-        .get(&auth)
+        .get(auth)
         .unwrap_or(&Header { header: None })
         .to_owned();
     let nurl = format!("{}/info/refs?service=git-upload-pack", url);
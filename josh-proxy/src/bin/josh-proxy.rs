@@ -1,4 +1,5 @@
 #![deny(warnings)]
+#![allow(clippy::needless_return)]
 #[macro_use]
 extern crate lazy_static;
 
@@ -6,6 +7,7 @@ use tracing_subscriber::Layer;
 
 use futures::future;
 use futures::FutureExt;
+use futures::StreamExt;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Request, Response, Server};
 use std::collections::HashMap;
@@ -43,6 +45,7 @@ struct JoshProxyService {
     fetch_permits: Arc<tokio::sync::Semaphore>,
     filter_permits: Arc<tokio::sync::Semaphore>,
     poll: Polls,
+    fetched: tokio::sync::broadcast::Sender<String>,
 }
 
 impl std::fmt::Debug for JoshProxyService {
@@ -66,10 +69,10 @@ async fn fetch_upstream(
     let auth = auth.clone();
     let key = remote_url.clone();
 
-    let refs_to_fetch = if headref != "" && !headref.starts_with("refs/heads/") {
-        vec!["refs/heads/*", "refs/tags/*", headref]
+    let refs_to_fetch = if !headref.is_empty() && !headref.starts_with("refs/heads/") {
+        vec!["refs/heads/*", "refs/tags/*", "refs/notes/*", headref]
     } else {
-        vec!["refs/heads/*", "refs/tags/*"]
+        vec!["refs/heads/*", "refs/tags/*", "refs/notes/*"]
     };
 
     let refs_to_fetch: Vec<_> = refs_to_fetch.iter().map(|x| x.to_string()).collect();
@@ -91,11 +94,11 @@ async fn fetch_upstream(
 
     tracing::trace!("fetch_cached_ok {:?}", fetch_cached_ok);
 
-    if fetch_cached_ok && headref == "" {
+    if fetch_cached_ok && headref.is_empty() {
         return Ok(true);
     }
 
-    if fetch_cached_ok && headref != "" {
+    if fetch_cached_ok && !headref.is_empty() {
         let transaction = josh::cache::Transaction::open(
             &service.repo_path,
             Some(&format!(
@@ -105,7 +108,7 @@ async fn fetch_upstream(
         )?;
         let id = transaction
             .repo()
-            .refname_to_id(&transaction.refname(&headref));
+            .refname_to_id(&transaction.refname(headref));
         tracing::trace!("refname_to_id: {:?}", id);
         if id.is_ok() {
             return Ok(true);
@@ -132,6 +135,9 @@ async fn fetch_upstream(
         if res {
             fetch_timers.write()?.insert(key, std::time::Instant::now());
 
+            // Nobody listening is not an error
+            service.fetched.send(upstream_repo.clone()).ok();
+
             if ARGS.value_of("poll") == Some(&auth.parse()?.0) {
                 service
                     .poll
@@ -146,7 +152,10 @@ async fn fetch_upstream(
 
 async fn static_paths(
     service: &JoshProxyService,
+    auth: &josh_proxy::auth::Handle,
+    method: &hyper::Method,
     path: &str,
+    query: Option<&str>,
 ) -> josh::JoshResult<Option<Response<hyper::Body>>> {
     tracing::debug!("static_path {:?}", path);
     if path == "/version" {
@@ -154,7 +163,7 @@ async fn static_paths(
             Response::builder()
                 .status(hyper::StatusCode::OK)
                 .body(hyper::Body::from(version_str()))
-                .unwrap_or(Response::default()),
+                .unwrap_or_default(),
         ));
     }
     if path == "/flush" {
@@ -163,7 +172,7 @@ async fn static_paths(
             Response::builder()
                 .status(hyper::StatusCode::OK)
                 .body(hyper::Body::from("Flushed credential cache\n"))
-                .unwrap_or(Response::default()),
+                .unwrap_or_default(),
         ));
     }
     if path == "/filters" || path == "/filters/refresh" {
@@ -185,7 +194,88 @@ async fn static_paths(
             Response::builder()
                 .status(hyper::StatusCode::OK)
                 .body(hyper::Body::from(body_str))
-                .unwrap_or(Response::default()),
+                .unwrap_or_default(),
+        ));
+    }
+    if path == "/filters/drop" && ARGS.is_present("filter-drop") {
+        if *method != hyper::Method::POST {
+            return Ok(Some(
+                Response::builder()
+                    .status(hyper::StatusCode::METHOD_NOT_ALLOWED)
+                    .body(hyper::Body::from("Use POST to drop a filter\n"))
+                    .unwrap_or_default(),
+            ));
+        }
+        let param = |name: &str| {
+            query.and_then(|q| {
+                url::form_urlencoded::parse(q.as_bytes())
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.to_string())
+            })
+        };
+
+        // Only users with access to the upstream repository may drop the filters of it
+        let upstream_repo = match param("repo") {
+            Some(repo) if repo.starts_with('/') && repo.ends_with(".git") => repo,
+            _ => {
+                return Ok(Some(
+                    Response::builder()
+                        .status(hyper::StatusCode::BAD_REQUEST)
+                        .body(hyper::Body::from(
+                            "Missing or invalid repo parameter, use repo=/<path>.git\n",
+                        ))
+                        .unwrap_or_default(),
+                ))
+            }
+        };
+        let remote_url = [service.upstream_url.as_str(), upstream_repo.as_str()].join("");
+        if !josh_proxy::auth::check_auth(&remote_url, auth, true).await? {
+            return Ok(Some(
+                Response::builder()
+                    .header("WWW-Authenticate", "Basic realm=User Visible Realm")
+                    .status(hyper::StatusCode::UNAUTHORIZED)
+                    .body(hyper::Body::empty())
+                    .unwrap_or_default(),
+            ));
+        }
+
+        let filter = param("filter").map(|spec| josh::filter::parse(&spec));
+        let filter = match filter {
+            Some(Ok(filter)) => filter,
+            Some(Err(e)) => {
+                return Ok(Some(
+                    Response::builder()
+                        .status(hyper::StatusCode::BAD_REQUEST)
+                        .body(hyper::Body::from(format!("Invalid filter: {}\n", e.0)))
+                        .unwrap_or_default(),
+                ))
+            }
+            None => {
+                return Ok(Some(
+                    Response::builder()
+                        .status(hyper::StatusCode::BAD_REQUEST)
+                        .body(hyper::Body::from("Missing filter parameter\n"))
+                        .unwrap_or_default(),
+                ))
+            }
+        };
+        let service = service.clone();
+
+        let body_str = tokio::task::spawn_blocking(move || -> josh::JoshResult<_> {
+            let transaction = josh::cache::Transaction::open(&service.repo_path, None)?;
+            Ok(if josh::housekeeping::drop_filter(&transaction, filter)? {
+                format!("Dropped cache for {}\n", josh::filter::spec(filter))
+            } else {
+                format!("Nothing cached for {}\n", josh::filter::spec(filter))
+            })
+        })
+        .await??;
+
+        return Ok(Some(
+            Response::builder()
+                .status(hyper::StatusCode::OK)
+                .body(hyper::Body::from(body_str))
+                .unwrap_or_default(),
         ));
     }
     return Ok(None);
@@ -203,7 +293,7 @@ async fn repo_update_fn(
         let _e = s.enter();
         let body = body?;
         let buffer = std::str::from_utf8(&body)?;
-        josh_proxy::process_repo_update(serde_json::from_str(&buffer)?)
+        josh_proxy::process_repo_update(serde_json::from_str(buffer)?)
     })
     .await?;
 
@@ -217,7 +307,35 @@ async fn repo_update_fn(
     }?);
 }
 
-#[tracing::instrument]
+/// Aborts filtering when the request is dropped, for example because the client
+/// disconnected.
+struct CancelOnDrop(josh::cache::Cancellation);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+struct TraceProgress;
+
+impl josh::cache::Progress for TraceProgress {
+    fn walk(&self, filter: josh::filter::Filter, n_commits: usize, n_new: usize) {
+        tracing::trace!(
+            "filtered {}/{} commits with {}",
+            n_commits,
+            n_new,
+            josh::filter::spec(filter)
+        );
+    }
+
+    fn refs(&self, n_refs: usize, total: usize) {
+        tracing::trace!("filtered {}/{} refs", n_refs, total);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(progress))]
 async fn do_filter(
     repo_path: std::path::PathBuf,
     service: Arc<JoshProxyService>,
@@ -225,8 +343,12 @@ async fn do_filter(
     temp_ns: Arc<josh_proxy::TmpGitNamespace>,
     filter_spec: String,
     headref: String,
-) -> josh::JoshResult<()> {
+    depth: Option<usize>,
+    progress: Arc<dyn josh::cache::Progress>,
+) -> josh::JoshResult<String> {
     let permit = service.filter_permits.acquire().await;
+    let cancel_on_drop = CancelOnDrop(josh::cache::Cancellation::new());
+    let cancellation = cancel_on_drop.0.clone();
 
     let s = tracing::span!(tracing::Level::TRACE, "do_filter worker");
     let r = tokio::task::spawn_blocking(move || {
@@ -239,11 +361,46 @@ async fn do_filter(
                 &josh::to_ns(&upstream_repo),
             )),
         )?;
+        transaction.set_cancellation(cancellation);
+        transaction.set_progress(progress);
         let filter = josh::filter::parse(&filter_spec)?;
+        let upstream_head = format!(
+            "refs/josh/upstream/{}/{}",
+            &josh::to_ns(&upstream_repo),
+            headref
+        );
+
+        if let Some(depth) = depth {
+            // Depth limited views only contain the requested head, filtered with
+            // a cutoff so that only the most recent history gets walked
+            let tip = transaction.repo().refname_to_id(&upstream_head)?;
+            let cutoff_ref = format!(
+                "refs/josh/depth/{}/{}/{}/{}",
+                josh::to_ns(&upstream_repo),
+                filter.id(),
+                depth,
+                headref
+            );
+            let filter =
+                josh_proxy::depth_cutoff(transaction.repo(), filter, &cutoff_ref, tip, depth)?;
+            josh::filter_refs(
+                &transaction,
+                filter,
+                &[(upstream_head, temp_ns.reference(&headref))],
+            )?;
+            transaction.repo().reference_symbolic(
+                &temp_ns.reference("HEAD"),
+                &temp_ns.reference(&headref),
+                true,
+                "",
+            )?;
+            return Ok(josh::filter::spec(filter));
+        }
+
         let filter_spec = josh::filter::spec(filter);
         let mut from_to = josh::housekeeping::default_from_to(
-            &transaction.repo(),
-            &temp_ns.name(),
+            transaction.repo(),
+            temp_ns.name(),
             &upstream_repo,
             &filter_spec,
         );
@@ -257,21 +414,14 @@ async fn do_filter(
             let reference = reference.unwrap();
             let refname = reference.name().unwrap();
             transaction.repo().reference(
-                &temp_ns.reference(&refname),
+                &temp_ns.reference(refname),
                 reference.target().unwrap(),
                 true,
                 "rewrite",
             )?;
         }
 
-        from_to.push((
-            format!(
-                "refs/josh/upstream/{}/{}",
-                &josh::to_ns(&upstream_repo),
-                headref
-            ),
-            temp_ns.reference(&headref),
-        ));
+        from_to.push((upstream_head, temp_ns.reference(&headref)));
 
         josh::filter_refs(&transaction, filter, &from_to)?;
         transaction.repo().reference_symbolic(
@@ -280,7 +430,7 @@ async fn do_filter(
             true,
             "",
         )?;
-        return Ok(());
+        return Ok(filter_spec);
     })
     .await?;
 
@@ -336,7 +486,7 @@ async fn call_service(
     {
         let p = &path[9..];
 
-        let result = hyper_staticfile::resolve_path("static", &p).await?;
+        let result = hyper_staticfile::resolve_path("static", p).await?;
         let result = if let hyper_staticfile::ResolveResult::NotFound = result {
             hyper_staticfile::resolve_path("static", "index.html").await?
         } else {
@@ -350,7 +500,7 @@ async fn call_service(
         return Ok(r);
     }
 
-    if let Some(r) = static_paths(&serv, &path).await? {
+    if let Some(r) = static_paths(&serv, &auth, req.method(), &path, req.uri().query()).await? {
         return Ok(r);
     }
 
@@ -361,7 +511,7 @@ async fn call_service(
     let parsed_url = {
         if let Some(parsed_url) = FilteredRepoUrl::from_str(&path) {
             let mut pu = parsed_url;
-            if pu.filter == "" {
+            if pu.filter.is_empty() {
                 pu.filter = ":/".to_string();
             }
             pu
@@ -372,8 +522,19 @@ async fn call_service(
         }
     };
 
+    let (filter_spec, depth) = match josh_proxy::split_depth(&parsed_url.filter) {
+        Ok(r) => r,
+        Err(e) => {
+            return Ok(Response::builder()
+                .status(hyper::StatusCode::UNPROCESSABLE_ENTITY)
+                .body(hyper::Body::from(e.to_string()))?)
+        }
+    };
+    let mut parsed_url = parsed_url;
+    parsed_url.filter = filter_spec;
+
     let mut headref = parsed_url.headref.trim_start_matches("@").to_owned();
-    if headref == "" {
+    if headref.is_empty() {
         headref = "refs/heads/master".to_string();
     }
 
@@ -431,13 +592,56 @@ async fn call_service(
     }
 
     if parsed_url.api == "/~/graphql" {
-        let ctx = std::sync::Arc::new(josh::graphql::context(josh::cache::Transaction::open(
-            &serv.repo_path,
-            Some(&format!(
-                "refs/josh/upstream/{}/",
-                &josh::to_ns(&parsed_url.upstream_repo),
-            )),
-        )?));
+        let repo_path = serv.repo_path.clone();
+        let push_url = remote_url.clone();
+        let push_auth = auth.clone();
+        let push_port = serv.port.clone();
+        let push_ns = josh::to_ns(&parsed_url.upstream_repo);
+        let fetched = serv.fetched.clone();
+        let upstream_repo = parsed_url.upstream_repo.clone();
+        let ctx = josh::graphql::context_with_push(
+            josh::cache::Transaction::open(
+                &serv.repo_path,
+                Some(&format!(
+                    "refs/josh/upstream/{}/",
+                    &josh::to_ns(&parsed_url.upstream_repo),
+                )),
+            )?,
+            std::sync::Arc::new(move |refname, filter, old, new| {
+                let temp_ns =
+                    josh_proxy::TmpGitNamespace::new(&repo_path, tracing::Span::current());
+                let repo_update = josh_proxy::RepoUpdate {
+                    refs: HashMap::new(),
+                    remote_url: push_url.clone(),
+                    auth: push_auth.clone(),
+                    port: push_port.clone(),
+                    filter_spec: josh::filter::spec(filter),
+                    base_ns: push_ns.clone(),
+                    git_ns: temp_ns.name().to_string(),
+                    git_dir: repo_path.to_string_lossy().to_string(),
+                };
+                josh_proxy::push_commit(&repo_update, refname, old, new)
+            }),
+        )
+        .with_watch(Box::new(move || {
+            let upstream_repo = upstream_repo.clone();
+            futures::stream::unfold(fetched.subscribe(), move |mut rx| {
+                let upstream_repo = upstream_repo.clone();
+                async move {
+                    loop {
+                        match rx.recv().await {
+                            Ok(repo) if repo == upstream_repo => return Some(((), rx)),
+                            Ok(_) => continue,
+                            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                                return Some(((), rx))
+                            }
+                            Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                        }
+                    }
+                }
+            })
+            .boxed()
+        }));
         let root_node = std::sync::Arc::new(josh::graphql::repo_schema(
             parsed_url
                 .upstream_repo
@@ -445,9 +649,18 @@ async fn call_service(
                 .unwrap_or(&parsed_url.upstream_repo)
                 .to_string(),
         ));
-        return Ok(josh_proxy::juniper_hyper::graphql(root_node, ctx, req)
-            .in_current_span()
-            .await?);
+        if req.headers().get(hyper::header::UPGRADE)
+            == Some(&hyper::header::HeaderValue::from_static("websocket"))
+        {
+            return Ok(josh_proxy::juniper_hyper::graphql_ws(root_node, ctx, req)
+                .in_current_span()
+                .await?);
+        }
+        return Ok(
+            josh_proxy::juniper_hyper::graphql(root_node, std::sync::Arc::new(ctx), req)
+                .in_current_span()
+                .await?,
+        );
     }
 
     if req.uri().query() == Some("info") {
@@ -473,116 +686,272 @@ async fn call_service(
             .body(hyper::Body::from(format!("{}\n", info_str)))?);
     }
 
-    let repo_path = serv
-        .repo_path
-        .to_str()
-        .ok_or(josh::josh_error("repo_path.to_str"))?;
+    // Clients fetching with sideband support get to see the progress of the filtering
+    // done for their request.
+    let mut wants_progress = false;
+    let mut wants_sideband_all = false;
+    let req = if parsed_url.pathinfo == "/git-upload-pack" && req.method() == hyper::Method::POST {
+        let (parts, body) = req.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+        wants_progress = josh_proxy::sideband::wants_progress(
+            &body,
+            parts
+                .headers
+                .get(hyper::header::CONTENT_ENCODING)
+                .and_then(|x| x.to_str().ok()),
+        );
+        wants_sideband_all = josh_proxy::sideband::wants_sideband_all(&body);
+        Request::from_parts(parts, hyper::Body::from(body))
+    } else {
+        req
+    };
 
-    let temp_ns = prepare_namespace(
-        serv.clone(),
-        &parsed_url.upstream_repo,
-        &parsed_url.filter,
-        &headref,
-    )
-    .in_current_span()
-    .await?;
+    // When all of the response is sideband data the progress is sent while filtering,
+    // otherwise it gets inserted into the response of upload-pack.
+    let streaming = wants_progress && wants_sideband_all;
+    let (packets, received) = tokio::sync::mpsc::unbounded_channel();
+    let sideband_progress = Arc::new(if streaming {
+        josh_proxy::sideband::Progress::streaming(packets.clone())
+    } else {
+        josh_proxy::sideband::Progress::new()
+    });
+    let progress: Arc<dyn josh::cache::Progress> = if wants_progress {
+        sideband_progress.clone()
+    } else {
+        Arc::new(TraceProgress)
+    };
 
-    if let Some(q) = req.uri().query().map(|x| x.to_string()) {
-        if parsed_url.pathinfo == "" {
-            let s = tracing::span!(tracing::Level::TRACE, "render worker");
-            let res = tokio::task::spawn_blocking(move || -> josh::JoshResult<_> {
-                let _e = s.enter();
-                let transaction = josh::cache::Transaction::open(
-                    &serv.repo_path,
-                    Some(&format!(
-                        "refs/josh/upstream/{}/",
-                        &josh::to_ns(&parsed_url.upstream_repo),
-                    )),
-                )?;
-
-                josh::query::render(transaction.repo(), "", &temp_ns.reference(&headref), &q)
-            })
+    // Protocol v2 clients only get the capabilities from info/refs and list the refs
+    // with a later request, so there is nothing to filter yet.
+    let git_protocol = req
+        .headers()
+        .get("Git-Protocol")
+        .and_then(|x| x.to_str().ok())
+        .map(String::from);
+    let advertise_refs = !(parsed_url.pathinfo == "/info/refs"
+        && req.uri().query() == Some("service=git-upload-pack")
+        && git_protocol
+            .as_deref()
+            .map(|x| x.contains("version=2"))
+            .unwrap_or(false));
+
+    let serve: UploadPack = Box::pin(
+        async move {
+            let (temp_ns, filter_spec) = prepare_namespace(
+                serv.clone(),
+                &parsed_url.upstream_repo,
+                &parsed_url.filter,
+                &headref,
+                depth,
+                advertise_refs,
+                progress,
+            )
             .in_current_span()
             .await?;
-            match res {
-                Ok(res) => {
-                    if let Some(res) = res {
-                        return Ok(Response::builder()
-                            .status(hyper::StatusCode::OK)
-                            .body(hyper::Body::from(res))?);
-                    } else {
-                        return Ok(Response::builder()
-                            .status(hyper::StatusCode::NOT_FOUND)
-                            .body(hyper::Body::from("File not found".to_string()))?);
+
+            if let Some(q) = req.uri().query().map(|x| x.to_string()) {
+                if parsed_url.pathinfo.is_empty() {
+                    let s = tracing::span!(tracing::Level::TRACE, "render worker");
+                    let res = tokio::task::spawn_blocking(move || -> josh::JoshResult<_> {
+                        let _e = s.enter();
+                        let transaction = josh::cache::Transaction::open(
+                            &serv.repo_path,
+                            Some(&format!(
+                                "refs/josh/upstream/{}/",
+                                &josh::to_ns(&parsed_url.upstream_repo),
+                            )),
+                        )?;
+
+                        josh::query::render(
+                            transaction.repo(),
+                            "",
+                            &temp_ns.reference(&headref),
+                            &q,
+                        )
+                    })
+                    .in_current_span()
+                    .await?;
+                    match res {
+                        Ok(res) => {
+                            if let Some(res) = res {
+                                return Ok(Response::builder()
+                                    .status(hyper::StatusCode::OK)
+                                    .body(hyper::Body::from(res))?);
+                            } else {
+                                return Ok(Response::builder()
+                                    .status(hyper::StatusCode::NOT_FOUND)
+                                    .body(hyper::Body::from("File not found".to_string()))?);
+                            }
+                        }
+                        Err(res) => {
+                            return Ok(Response::builder()
+                                .status(hyper::StatusCode::UNPROCESSABLE_ENTITY)
+                                .body(hyper::Body::from(res.to_string()))?)
+                        }
                     }
                 }
-                Err(res) => {
-                    return Ok(Response::builder()
-                        .status(hyper::StatusCode::UNPROCESSABLE_ENTITY)
-                        .body(hyper::Body::from(res.to_string()))?)
-                }
             }
+
+            let repo_path = serv
+                .repo_path
+                .to_str()
+                .ok_or(josh::josh_error("repo_path.to_str"))?;
+
+            let repo_update = josh_proxy::RepoUpdate {
+                refs: HashMap::new(),
+                remote_url: remote_url.clone(),
+                auth,
+                port: serv.port.clone(),
+                filter_spec,
+                base_ns: josh::to_ns(&parsed_url.upstream_repo),
+                git_ns: temp_ns.name().to_string(),
+                git_dir: repo_path.to_string(),
+            };
+
+            let mut cmd = Command::new("git");
+            cmd.arg("http-backend");
+            if let Some(protocol) = &git_protocol {
+                cmd.env("GIT_PROTOCOL", protocol);
+            }
+            cmd.current_dir(&serv.repo_path);
+            cmd.env("GIT_DIR", repo_path);
+            cmd.env("GIT_HTTP_EXPORT_ALL", "");
+            cmd.env("GIT_NAMESPACE", temp_ns.name());
+            cmd.env("GIT_PROJECT_ROOT", repo_path);
+            cmd.env("JOSH_REPO_UPDATE", serde_json::to_string(&repo_update)?);
+            cmd.env("PATH_INFO", parsed_url.pathinfo.clone());
+
+            let cgires = hyper_cgi::do_cgi(req, cmd)
+                .instrument(tracing::span!(tracing::Level::TRACE, "git http-backend"))
+                .await
+                .0;
+
+            // This is chained as a seperate future to make sure that
+            // it is executed in all cases.
+            std::mem::drop(temp_ns);
+
+            return Ok(cgires);
         }
+        .in_current_span(),
+    );
+
+    if streaming {
+        return stream_upload_pack(serve, packets, received);
     }
 
-    let repo_update = josh_proxy::RepoUpdate {
-        refs: HashMap::new(),
-        remote_url: remote_url.clone(),
-        auth,
-        port: serv.port.clone(),
-        filter_spec: parsed_url.filter.clone(),
-        base_ns: josh::to_ns(&parsed_url.upstream_repo),
-        git_ns: temp_ns.name().to_string(),
-        git_dir: repo_path.to_string(),
-    };
+    let cgires = serve.await?;
+    if wants_progress {
+        let (parts, body) = cgires.into_parts();
+        return Ok(Response::from_parts(
+            parts,
+            josh_proxy::sideband::inject(body, sideband_progress.take_messages()),
+        ));
+    }
 
-    let mut cmd = Command::new("git");
-    cmd.arg("http-backend");
-    cmd.current_dir(&serv.repo_path);
-    cmd.env("GIT_DIR", repo_path);
-    cmd.env("GIT_HTTP_EXPORT_ALL", "");
-    cmd.env("GIT_NAMESPACE", temp_ns.name().clone());
-    cmd.env("GIT_PROJECT_ROOT", repo_path);
-    cmd.env("JOSH_REPO_UPDATE", serde_json::to_string(&repo_update)?);
-    cmd.env("PATH_INFO", parsed_url.pathinfo.clone());
-
-    let cgires = hyper_cgi::do_cgi(req, cmd)
-        .instrument(tracing::span!(tracing::Level::TRACE, "git http-backend"))
-        .await
-        .0;
+    return Ok(cgires);
+}
 
-    // This is chained as a seperate future to make sure that
-    // it is executed in all cases.
-    std::mem::drop(temp_ns);
+type UploadPack = std::pin::Pin<
+    Box<dyn std::future::Future<Output = josh::JoshResult<Response<hyper::Body>>> + Send>,
+>;
+
+/// Respond to an upload-pack request right away, with `received` as the body. `serve`
+/// runs in the background, while the progress of the filtering arrives as sideband
+/// `packets`, followed by the response of upload-pack.
+/// Only valid for clients that asked for all of the response to be sideband data.
+fn stream_upload_pack(
+    serve: UploadPack,
+    packets: tokio::sync::mpsc::UnboundedSender<Vec<u8>>,
+    received: tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>,
+) -> josh::JoshResult<Response<hyper::Body>> {
+    let error =
+        |msg: String| josh_proxy::sideband::packet(3, format!("josh: {}\n", msg).as_bytes());
+
+    tokio::spawn(
+        async move {
+            let response = tokio::select! {
+                response = serve => response,
+                // Dropping `serve` cancels the filtering
+                _ = packets.closed() => return,
+            };
+            let mut body = match response {
+                Ok(response) if response.status() == hyper::StatusCode::OK => response.into_body(),
+                Ok(response) => {
+                    packets
+                        .send(error(format!("upload-pack failed: {}", response.status())))
+                        .ok();
+                    return;
+                }
+                Err(e) => {
+                    packets.send(error(e.0)).ok();
+                    return;
+                }
+            };
+            while let Some(chunk) = body.next().await {
+                let sent = match chunk {
+                    Ok(chunk) => packets.send(chunk.to_vec()),
+                    Err(e) => packets.send(error(e.to_string())),
+                };
+                if sent.is_err() {
+                    return;
+                }
+            }
+        }
+        .in_current_span(),
+    );
 
-    return Ok(cgires);
+    let body = futures::stream::unfold(received, |mut received| async move {
+        let packet = received.recv().await?;
+        Some((
+            Ok::<_, hyper::Error>(hyper::body::Bytes::from(packet)),
+            received,
+        ))
+    });
+
+    return Ok(Response::builder()
+        .header(
+            hyper::header::CONTENT_TYPE,
+            "application/x-git-upload-pack-result",
+        )
+        .header(hyper::header::CACHE_CONTROL, "no-cache")
+        .body(hyper::Body::wrap_stream(body))?);
 }
 
-#[tracing::instrument]
+#[tracing::instrument(skip(progress))]
 async fn prepare_namespace(
     serv: Arc<JoshProxyService>,
     upstream_repo: &str,
     filter_spec: &str,
     headref: &str,
-) -> josh::JoshResult<std::sync::Arc<josh_proxy::TmpGitNamespace>> {
+    depth: Option<usize>,
+    advertise_refs: bool,
+    progress: Arc<dyn josh::cache::Progress>,
+) -> josh::JoshResult<(std::sync::Arc<josh_proxy::TmpGitNamespace>, String)> {
     let temp_ns = Arc::new(josh_proxy::TmpGitNamespace::new(
         &serv.repo_path,
         tracing::Span::current(),
     ));
 
+    if !advertise_refs {
+        let filter_spec = josh::filter::spec(josh::filter::parse(filter_spec)?);
+        return Ok((temp_ns, filter_spec));
+    }
+
     let serv = serv.clone();
 
-    do_filter(
+    let filter_spec = do_filter(
         serv.repo_path.clone(),
         serv.clone(),
         upstream_repo.to_owned(),
         temp_ns.to_owned(),
         filter_spec.to_owned(),
         headref.to_string(),
+        depth,
+        progress,
     )
     .await?;
 
-    return Ok(temp_ns);
+    return Ok((temp_ns, filter_spec));
 }
 
 #[tokio::main]
@@ -600,6 +969,19 @@ async fn run_proxy() -> josh::JoshResult<i32> {
 
     josh_proxy::create_repo(&local)?;
     josh::cache::load(&local)?;
+    josh::cache::set_jobs(ARGS.value_of("jobs").unwrap_or("1").parse()?);
+    josh::history::set_signature_policy(match ARGS.value_of("signatures") {
+        Some("strip") => josh::history::SignaturePolicy::Strip,
+        Some("resign") => josh::history::SignaturePolicy::Resign(
+            ARGS.value_of("signing-key")
+                .ok_or(josh::josh_error("--signatures=resign needs --signing-key"))?
+                .into(),
+        ),
+        _ => josh::history::SignaturePolicy::Preserve,
+    });
+    josh::history::set_tag_message_template(
+        ARGS.value_of("tag-message-template").map(String::from),
+    );
 
     let proxy_service = Arc::new(JoshProxyService {
         port,
@@ -611,6 +993,7 @@ async fn run_proxy() -> josh::JoshResult<i32> {
             ARGS.value_of("n").unwrap_or("1").parse()?,
         )),
         filter_permits: Arc::new(tokio::sync::Semaphore::new(10)),
+        fetched: tokio::sync::broadcast::channel(16).0,
     });
 
     let ps = proxy_service.clone();
@@ -685,7 +1068,7 @@ async fn run_polling(serv: Arc<JoshProxyService>) -> josh::JoshResult<()> {
                 upstream_repo.clone(),
                 &auth,
                 url.clone(),
-                &"",
+                "",
                 true,
             )
             .in_current_span()
@@ -699,8 +1082,12 @@ async fn run_housekeeping(local: std::path::PathBuf) -> josh::JoshResult<()> {
     let mut i: usize = 0;
     loop {
         let local = local.clone();
+        let retention = ARGS
+            .value_of("cache-retention")
+            .map(|x| x.parse().map(std::time::Duration::from_secs))
+            .transpose()?;
         tokio::task::spawn_blocking(move || {
-            josh::housekeeping::run(&local, (i % 60 == 0) && ARGS.is_present("gc"))
+            josh::housekeeping::run(&local, (i % 60 == 0) && ARGS.is_present("gc"), retention)
         })
         .await??;
         tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
@@ -735,6 +1122,18 @@ fn parse_args() -> clap::ArgMatches<'static> {
                 .takes_value(false)
                 .help("Run git gc in maintanance"),
         )
+        .arg(
+            clap::Arg::with_name("cache-retention")
+                .long("cache-retention")
+                .takes_value(true)
+                .help("Drop the cache of filters that were not used for this many seconds"),
+        )
+        .arg(
+            clap::Arg::with_name("filter-drop")
+                .long("filter-drop")
+                .help("Enable the /filters/drop endpoint, for users with access to the upstream repository")
+                .takes_value(false),
+        )
         .arg(
             clap::Arg::with_name("require-auth")
                 .long("require-auth")
@@ -757,6 +1156,33 @@ fn parse_args() -> clap::ArgMatches<'static> {
                 .takes_value(true)
                 .help("Number of concurrent upstream git fetch/push operations"),
         )
+        .arg(
+            clap::Arg::with_name("jobs")
+                .long("jobs")
+                .short("j")
+                .takes_value(true)
+                .help("Number of threads used to filter independent histories"),
+        )
+        .arg(
+            clap::Arg::with_name("signatures")
+                .long("signatures")
+                .possible_values(&["preserve", "strip", "resign"])
+                .default_value("preserve")
+                .takes_value(true)
+                .help("What to do with signatures of rewritten commits"),
+        )
+        .arg(
+            clap::Arg::with_name("signing-key")
+                .long("signing-key")
+                .takes_value(true)
+                .help("SSH key file used with --signatures=resign, runs ssh-keygen once per rewritten commit"),
+        )
+        .arg(
+            clap::Arg::with_name("tag-message-template")
+                .long("tag-message-template")
+                .takes_value(true)
+                .help("Template for the messages of filtered annotated tags"),
+        )
         .arg(clap::Arg::with_name("port").long("port").takes_value(true))
         .arg(
             clap::Arg::with_name("cache-duration")
@@ -772,10 +1198,10 @@ fn pre_receive_hook() -> josh::JoshResult<i32> {
     let repo_update: josh_proxy::RepoUpdate =
         serde_json::from_str(&std::env::var("JOSH_REPO_UPDATE")?)?;
 
-    let p = std::path::PathBuf::from(repo_update.git_dir)
-        .join("refs/namespaces")
-        .join(repo_update.git_ns)
-        .join("push_options");
+    let p = josh_proxy::push_options_path(
+        std::path::Path::new(&repo_update.git_dir),
+        &repo_update.git_ns,
+    );
 
     let n: usize = std::env::var("GIT_PUSH_OPTION_COUNT")?.parse()?;
 
@@ -789,6 +1215,9 @@ fn pre_receive_hook() -> josh::JoshResult<i32> {
         }
     }
 
+    if let Some(dir) = p.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
     std::fs::write(p, serde_json::to_string(&push_options)?)?;
 
     return Ok(0);
@@ -849,7 +1278,7 @@ fn main() {
     // cached data already loaded into the main processe's memory.
     if let [a0, a1, a2, a3, ..] = &std::env::args().collect::<Vec<_>>().as_slice() {
         if a0.ends_with("/update") {
-            std::process::exit(update_hook(&a1, &a2, &a3).unwrap_or(1));
+            std::process::exit(update_hook(a1, a2, a3).unwrap_or(1));
         }
     }
 
@@ -1,5 +1,6 @@
 use std::{error::Error, fmt, string::FromUtf8Error, sync::Arc};
 
+use futures::{future, SinkExt, StreamExt};
 use hyper::{
     header::{self, HeaderValue},
     Body, Method, Request, Response, StatusCode,
@@ -8,7 +9,9 @@ use juniper::{
     http::{GraphQLBatchRequest, GraphQLRequest as JuniperGraphQLRequest, GraphQLRequest},
     GraphQLSubscriptionType, GraphQLType, GraphQLTypeAsync, InputValue, RootNode, ScalarValue,
 };
+use juniper_graphql_ws::{ArcSchema, ClientMessage, Connection, ConnectionConfig};
 use serde_json::error::Error as SerdeError;
+use tokio_tungstenite::tungstenite::{handshake::derive_accept_key, protocol::Role, Message};
 use url::form_urlencoded;
 
 pub async fn graphql_sync<CtxT, QueryT, MutationT, SubscriptionT, S>(
@@ -53,6 +56,84 @@ where
     })
 }
 
+/// Upgrade the request to a websocket and serve subscriptions on it using
+/// the `graphql-ws` protocol
+pub async fn graphql_ws<CtxT, QueryT, MutationT, SubscriptionT, S>(
+    root_node: Arc<RootNode<'static, QueryT, MutationT, SubscriptionT, S>>,
+    context: CtxT,
+    req: Request<Body>,
+) -> Result<Response<Body>, hyper::Error>
+where
+    QueryT: GraphQLTypeAsync<S, Context = CtxT> + Send + 'static,
+    QueryT::TypeInfo: Send + Sync,
+    MutationT: GraphQLTypeAsync<S, Context = CtxT> + Send + 'static,
+    MutationT::TypeInfo: Send + Sync,
+    SubscriptionT: GraphQLSubscriptionType<S, Context = CtxT> + Send + 'static,
+    SubscriptionT::TypeInfo: Send + Sync,
+    CtxT: Unpin + Send + Sync + 'static,
+    S: ScalarValue + Send + Sync + 'static,
+{
+    let accept = match req.headers().get(header::SEC_WEBSOCKET_KEY) {
+        Some(key) => derive_accept_key(key.as_bytes()),
+        None => return Ok(new_response(StatusCode::BAD_REQUEST)),
+    };
+
+    tokio::spawn(async move {
+        let upgraded = match hyper::upgrade::on(req).await {
+            Ok(upgraded) => upgraded,
+            Err(e) => {
+                tracing::warn!("websocket upgrade failed: {}", e);
+                return;
+            }
+        };
+        let websocket =
+            tokio_tungstenite::WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+
+        let (ws_tx, ws_rx) = websocket.split();
+        let (s_tx, s_rx) =
+            Connection::new(ArcSchema(root_node), ConnectionConfig::new(context)).split();
+
+        let input = ws_rx.filter_map(|msg| {
+            future::ready(match msg {
+                Ok(Message::Text(text)) => {
+                    serde_json::from_str::<ClientMessage<S>>(&text).ok().map(Ok)
+                }
+                _ => None,
+            })
+        });
+        let output = s_rx.filter_map(|msg| {
+            future::ready(
+                serde_json::to_string(&msg)
+                    .ok()
+                    .map(|text| Ok(Message::Text(text))),
+            )
+        });
+
+        future::select(
+            Box::pin(input.forward(s_tx)),
+            Box::pin(output.forward(ws_tx.sink_map_err(|e| {
+                tracing::debug!("websocket closed: {}", e);
+            }))),
+        )
+        .await;
+    });
+
+    let mut resp = new_response(StatusCode::SWITCHING_PROTOCOLS);
+    resp.headers_mut()
+        .insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    resp.headers_mut()
+        .insert(header::CONNECTION, HeaderValue::from_static("Upgrade"));
+    resp.headers_mut().insert(
+        header::SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static("graphql-ws"),
+    );
+    if let Ok(accept) = HeaderValue::from_str(&accept) {
+        resp.headers_mut()
+            .insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+    }
+    Ok(resp)
+}
+
 async fn parse_req<S: ScalarValue>(
     req: Request<Body>,
 ) -> Result<GraphQLBatchRequest<S>, Response<Body>> {
@@ -284,13 +365,13 @@ enum GraphQLRequestError {
 }
 
 impl fmt::Display for GraphQLRequestError {
-    fn fmt(&self, mut f: &mut fmt::Formatter) -> fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            GraphQLRequestError::BodyHyper(ref err) => fmt::Display::fmt(err, &mut f),
-            GraphQLRequestError::BodyUtf8(ref err) => fmt::Display::fmt(err, &mut f),
-            GraphQLRequestError::BodyJSONError(ref err) => fmt::Display::fmt(err, &mut f),
-            GraphQLRequestError::Variables(ref err) => fmt::Display::fmt(err, &mut f),
-            GraphQLRequestError::Invalid(ref err) => fmt::Display::fmt(err, &mut f),
+            GraphQLRequestError::BodyHyper(ref err) => fmt::Display::fmt(err, f),
+            GraphQLRequestError::BodyUtf8(ref err) => fmt::Display::fmt(err, f),
+            GraphQLRequestError::BodyJSONError(ref err) => fmt::Display::fmt(err, f),
+            GraphQLRequestError::Variables(ref err) => fmt::Display::fmt(err, f),
+            GraphQLRequestError::Invalid(ref err) => fmt::Display::fmt(err, f),
         }
     }
 }
@@ -328,7 +409,9 @@ mod tests {
     impl http_tests::HttpIntegration for TestHyperIntegration {
         fn get(&self, url: &str) -> http_tests::TestResponse {
             let url = format!("http://127.0.0.1:{}/graphql{}", self.port, url);
-            make_test_response(reqwest::blocking::get(&url).expect(&format!("failed GET {}", url)))
+            make_test_response(
+                reqwest::blocking::get(&url).unwrap_or_else(|_| panic!("failed GET {}", url)),
+            )
         }
 
         fn post_json(&self, url: &str, body: &str) -> http_tests::TestResponse {
@@ -339,7 +422,7 @@ mod tests {
                 .header(reqwest::header::CONTENT_TYPE, "application/json")
                 .body(body.to_string())
                 .send()
-                .expect(&format!("failed POST {}", url));
+                .unwrap_or_else(|_| panic!("failed POST {}", url));
             make_test_response(res)
         }
 
@@ -351,7 +434,7 @@ mod tests {
                 .header(reqwest::header::CONTENT_TYPE, "application/graphql")
                 .body(body.to_string())
                 .send()
-                .expect(&format!("failed POST {}", url));
+                .unwrap_or_else(|_| panic!("failed POST {}", url));
             make_test_response(res)
         }
     }
@@ -360,7 +443,7 @@ mod tests {
         let status_code = response.status().as_u16() as i32;
         let content_type_header = response.headers().get(reqwest::header::CONTENT_TYPE);
         let content_type = if let Some(ct) = content_type_header {
-            format!("{}", ct.to_str().unwrap())
+            ct.to_str().unwrap().to_string()
         } else {
             String::default()
         };
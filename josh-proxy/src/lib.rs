@@ -1,5 +1,7 @@
+#![allow(clippy::needless_return)]
 pub mod auth;
 pub mod juniper_hyper;
+pub mod sideband;
 
 #[macro_use]
 extern crate lazy_static;
@@ -25,6 +27,77 @@ fn baseref_and_options(refname: &str) -> josh::JoshResult<(String, String, Vec<S
     return Ok((baseref, push_to, options));
 }
 
+/// Change-Id of the commit every `refs/changes/*` reference pointed to when it was last seen
+type ChangeIndex = std::collections::HashMap<String, (git2::Oid, Option<String>)>;
+
+lazy_static! {
+    static ref CHANGE_INDEX: std::sync::Mutex<std::collections::HashMap<String, ChangeIndex>> =
+        std::sync::Mutex::new(std::collections::HashMap::new());
+}
+
+/// Find the latest patchset of every Gerrit change of the upstream repo, by Change-Id.
+/// Reading commit messages is the expensive part, so only references that moved since
+/// the last call are looked at again.
+fn gerrit_amends(
+    repo: &git2::Repository,
+    base_ns: &str,
+) -> josh::JoshResult<std::collections::HashMap<String, git2::Oid>> {
+    let prefix = format!("refs/josh/upstream/{}/refs/changes/", base_ns);
+    let mut change_index = CHANGE_INDEX.lock()?;
+    let index = change_index
+        .entry(base_ns.to_string())
+        .or_insert_with(std::collections::HashMap::new);
+
+    let mut seen = std::collections::HashSet::new();
+    let mut latest = std::collections::HashMap::<String, ((u64, u64), git2::Oid)>::new();
+    for reference in repo.references_glob(&format!("{}*", prefix))? {
+        let reference = reference?;
+        let name = josh::some_or!(reference.name(), { continue }).to_string();
+        let target = josh::some_or!(reference.target(), { continue });
+
+        // refs/changes/<last two digits>/<change number>/<patchset>, the sharding
+        // directory is optional
+        let parts: Vec<_> = name.trim_start_matches(&prefix).split('/').collect();
+        let version = match parts[..] {
+            [change, patchset] | [_, change, patchset] => {
+                match (change.parse::<u64>(), patchset.parse::<u64>()) {
+                    (Ok(change), Ok(patchset)) => (change, patchset),
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+
+        let change_id = match index.get(&name) {
+            Some((oid, change_id)) if *oid == target => change_id.clone(),
+            _ => {
+                let change_id = repo
+                    .find_commit(target)
+                    .ok()
+                    .and_then(|commit| josh::get_change_id(&commit));
+                index.insert(name.clone(), (target, change_id.clone()));
+                change_id
+            }
+        };
+        seen.insert(name);
+
+        if let Some(change_id) = change_id {
+            match latest.get(&change_id) {
+                Some((v, _)) if *v >= version => {}
+                _ => {
+                    latest.insert(change_id, (version, target));
+                }
+            }
+        }
+    }
+    index.retain(|name, _| seen.contains(name));
+
+    return Ok(latest
+        .into_iter()
+        .map(|(change_id, (_, oid))| (change_id, oid))
+        .collect());
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct RepoUpdate {
     pub refs: std::collections::HashMap<String, (String, String)>,
@@ -37,228 +110,330 @@ pub struct RepoUpdate {
     pub git_dir: String,
 }
 
-pub fn process_repo_update(repo_update: RepoUpdate) -> josh::JoshResult<String> {
-    let mut resp = String::new();
+/// File the push options of the push into `git_ns` are stored in by the pre-receive hook.
+/// It is kept outside of `refs`, as git would choke on it when fetching during the push.
+pub fn push_options_path(git_dir: &std::path::Path, git_ns: &str) -> std::path::PathBuf {
+    return git_dir.join("josh_push_options").join(git_ns);
+}
 
-    let p = std::path::PathBuf::from(&repo_update.git_dir)
-        .join("refs/namespaces")
-        .join(&repo_update.git_ns)
-        .join("push_options");
+pub fn process_repo_update(repo_update: RepoUpdate) -> josh::JoshResult<String> {
+    let p = push_options_path(
+        std::path::Path::new(&repo_update.git_dir),
+        &repo_update.git_ns,
+    );
 
     let push_options_string = std::fs::read_to_string(p)?;
     let push_options: std::collections::HashMap<String, String> =
         serde_json::from_str(&push_options_string)?;
 
-    for (refname, (old, new)) in repo_update.refs.iter() {
-        tracing::debug!("REPO_UPDATE env ok");
+    // The update hook is called once per ref
+    if let Some((refname, (old, new))) = repo_update.refs.iter().next() {
+        let (resp, _) = push_ref(&repo_update, &push_options, refname, old, new)?;
+        return Ok(resp);
+    }
 
-        let transaction = josh::cache::Transaction::open(
-            &std::path::Path::new(&repo_update.git_dir),
-            Some(&format!("refs/josh/upstream/{}/", repo_update.base_ns)),
-        )?;
+    return Ok("".to_string());
+}
+
+/// Map the update of the filtered `refname` from `old` to `new` back to the upstream
+/// repository and push it there. Returns the output of the push and the pushed commit.
+pub fn push_ref(
+    repo_update: &RepoUpdate,
+    push_options: &std::collections::HashMap<String, String>,
+    refname: &str,
+    old: &str,
+    new: &str,
+) -> josh::JoshResult<(String, git2::Oid)> {
+    tracing::debug!("REPO_UPDATE env ok");
+
+    let transaction = josh::cache::Transaction::open(
+        std::path::Path::new(&repo_update.git_dir),
+        Some(&format!("refs/josh/upstream/{}/", repo_update.base_ns)),
+    )?;
 
-        let old = git2::Oid::from_str(old)?;
+    let old = git2::Oid::from_str(old)?;
 
-        let (baseref, push_to, options) = baseref_and_options(refname)?;
-        let josh_merge = push_options.contains_key("merge");
+    let (baseref, push_to, options) = baseref_and_options(refname)?;
+    let josh_merge = push_options.contains_key("merge");
 
-        tracing::debug!("push options: {:?}", push_options);
-        tracing::debug!("josh-merge: {:?}", josh_merge);
+    tracing::debug!("push options: {:?}", push_options);
+    tracing::debug!("josh-merge: {:?}", josh_merge);
 
-        let old = if old == git2::Oid::zero() {
-            let rev = format!("refs/namespaces/{}/{}", repo_update.git_ns, &baseref);
-            let oid = if let Ok(x) = transaction.repo().revparse_single(&rev) {
-                x.id()
-            } else {
-                old
-            };
-            tracing::debug!("push: old oid: {:?}, rev: {:?}", oid, rev);
-            oid
+    let old = if old == git2::Oid::zero() {
+        let rev = format!("refs/namespaces/{}/{}", repo_update.git_ns, &baseref);
+        let oid = if let Ok(x) = transaction.repo().revparse_single(&rev) {
+            x.id()
         } else {
-            tracing::debug!("push: old oid: {:?}, refname: {:?}", old, refname);
             old
         };
+        tracing::debug!("push: old oid: {:?}, rev: {:?}", oid, rev);
+        oid
+    } else {
+        tracing::debug!("push: old oid: {:?}, refname: {:?}", old, refname);
+        old
+    };
 
-        let original_target_ref = if let Some(base) = push_options.get("base") {
-            transaction.refname(&base)
-        } else {
-            transaction.refname(&baseref)
-        };
+    if josh::is_notes_ref(&baseref) {
+        let (text, status) = push_notes(
+            &transaction,
+            repo_update,
+            &baseref,
+            &push_to,
+            old,
+            git2::Oid::from_str(new)?,
+        )?;
+        if status == 0 {
+            return Ok((text, git2::Oid::from_str(new)?));
+        }
+        return Err(josh::josh_error(&text));
+    }
 
-        let original_target =
-            if let Ok(oid) = transaction.repo().refname_to_id(&original_target_ref) {
-                tracing::debug!(
-                    "push: original_target oid: {:?}, original_target_ref: {:?}",
-                    oid,
-                    original_target_ref
-                );
-                oid
-            } else {
-                return Err(josh::josh_error(&unindent::unindent(&format!(
-                    r###"
-                    Reference {:?} does not exist on remote.
-                    If you want to create it, pass "-o base=refs/heads/<branchname>"
-                    to specify a base branch/reference.
-                    "###,
-                    baseref
-                ))));
-            };
-
-        let reparent_orphans = if push_options.contains_key("create") {
-            Some(original_target)
-        } else {
-            None
-        };
+    if let Some(base) = push_options.get("base") {
+        if !base.starts_with("refs/") || !git2::Reference::is_valid_name(base) {
+            return Err(josh::josh_error(&format!(
+                "invalid base {:?}: must be a full reference name like refs/heads/<branchname>",
+                base
+            )));
+        }
+    }
 
-        let amends = std::collections::HashMap::new();
-        //let amends = {
-        //    let gerrit_changes = format!(
-        //        "refs/josh/upstream/{}/refs/changes/*",
-        //        repo_update.base_ns,
-        //    );
-        //    let mut amends = std::collections::HashMap::new();
-        //    for reference in
-        //        transaction.repo().references_glob(&gerrit_changes)?
-        //    {
-        //        if let Ok(commit) = transaction.repo().find_commit(
-        //            reference?.target().unwrap_or(git2::Oid::zero()),
-        //        ) {
-        //            if let Some(id) = josh::get_change_id(&commit) {
-        //                amends.insert(id, commit.id());
-        //            }
-        //        }
-        //    }
-        //    amends
-        //};
-
-        let filterobj = josh::filter::parse(&repo_update.filter_spec)?;
-        let new_oid = git2::Oid::from_str(&new)?;
-        let backward_new_oid = {
-            tracing::debug!("=== MORE");
-
-            tracing::debug!("=== processed_old {:?}", old);
-
-            match josh::history::unapply_filter(
-                &transaction,
-                filterobj,
-                original_target,
-                old,
-                new_oid,
-                josh_merge,
-                reparent_orphans,
-                &amends,
-            )? {
-                josh::UnapplyResult::Done(rewritten) => {
-                    tracing::debug!("rewritten");
-                    rewritten
-                }
-                josh::UnapplyResult::BranchDoesNotExist => {
-                    return Err(josh::josh_error("branch does not exist on remote"));
-                }
-                josh::UnapplyResult::RejectMerge(parent_count) => {
-                    return Err(josh::josh_error(&format!(
-                        "rejecting merge with {} parents",
-                        parent_count
-                    )));
-                }
-                josh::UnapplyResult::RejectAmend(msg) => {
-                    return Err(josh::josh_error(&format!(
-                        "rejecting to amend {:?} with conflicting changes",
-                        msg
-                    )));
-                }
-            }
-        };
+    let josh_rebase = push_options.contains_key("rebase");
 
-        let oid_to_push = if josh_merge {
-            let backward_commit = transaction.repo().find_commit(backward_new_oid)?;
-            if let Ok(Ok(base_commit)) = transaction
-                .repo()
-                .revparse_single(&original_target_ref)
-                .map(|x| x.peel_to_commit())
-            {
-                let merged_tree = transaction
-                    .repo()
-                    .merge_commits(&base_commit, &backward_commit, None)?
-                    .write_tree_to(&transaction.repo())?;
-                transaction.repo().commit(
-                    None,
-                    &backward_commit.author(),
-                    &backward_commit.committer(),
-                    &format!("Merge from {}", &repo_update.filter_spec),
-                    &transaction.repo().find_tree(merged_tree)?,
-                    &[&base_commit, &backward_commit],
-                )?
-            } else {
-                return Err(josh::josh_error("josh_merge failed"));
-            }
-        } else {
-            backward_new_oid
-        };
+    if josh_rebase {
+        // Rebasing only makes sense onto the current state of the upstream branch
+        let target = push_options.get("base").unwrap_or(&baseref);
+        if !fetch_refs_from_url(
+            std::path::Path::new(&repo_update.git_dir),
+            &josh::from_ns(&repo_update.base_ns),
+            &repo_update.remote_url,
+            &[target.to_string()],
+            &repo_update.auth,
+        )? {
+            return Err(josh::josh_error("can't fetch from upstream"));
+        }
+    }
 
-        let push_with_options = if options.len() != 0 {
-            format!("{}{}{}", push_to, "%", options.join(","))
-        } else {
-            push_to
-        };
+    let original_target_ref = if let Some(base) = push_options.get("base") {
+        transaction.refname(base)
+    } else {
+        transaction.refname(&baseref)
+    };
 
-        let reapply = josh::filter::apply_to_commit(
-            filterobj,
-            &transaction.repo().find_commit(oid_to_push)?,
-            &transaction,
-        )?;
+    let original_target = if let Ok(oid) = transaction.repo().refname_to_id(&original_target_ref) {
+        tracing::debug!(
+            "push: original_target oid: {:?}, original_target_ref: {:?}",
+            oid,
+            original_target_ref
+        );
+        oid
+    } else {
+        return Err(josh::josh_error(&unindent::unindent(&format!(
+            r###"
+                Reference {:?} does not exist on remote.
+                If you want to create it, pass "-o base=refs/heads/<branchname>"
+                to specify a base branch/reference.
+                "###,
+            baseref
+        ))));
+    };
+
+    let reparent_orphans = if push_options.contains_key("create") {
+        Some(original_target)
+    } else {
+        None
+    };
 
-        let (text, status) = push_head_url(
-            &transaction.repo(),
-            oid_to_push,
-            &push_with_options,
+    let amends = if push_options.contains_key("amend") {
+        if !fetch_refs_from_url(
+            std::path::Path::new(&repo_update.git_dir),
+            &josh::from_ns(&repo_update.base_ns),
             &repo_update.remote_url,
+            &["refs/changes/*".to_string()],
             &repo_update.auth,
-            &repo_update.git_ns,
-        )?;
+        )? {
+            return Err(josh::josh_error("can't fetch changes from upstream"));
+        }
+        gerrit_amends(transaction.repo(), &repo_update.base_ns)?
+    } else {
+        std::collections::HashMap::new()
+    };
 
-        let warnings = josh::filter::compute_warnings(
+    let filterobj = josh::filter::parse(&repo_update.filter_spec)?;
+    let new_oid = git2::Oid::from_str(new)?;
+    let backward_new_oid = {
+        tracing::debug!("=== MORE");
+
+        tracing::debug!("=== processed_old {:?}", old);
+
+        match josh::history::unapply_filter(
             &transaction,
             filterobj,
-            transaction.repo().find_commit(oid_to_push)?.tree()?,
-        );
-
-        let mut warning_str = "".to_owned();
-        if warnings.len() > 0 {
-            let warnings = warnings.iter();
+            original_target,
+            old,
+            new_oid,
+            josh_merge,
+            reparent_orphans,
+            &amends,
+        )? {
+            josh::UnapplyResult::Done(rewritten) => {
+                tracing::debug!("rewritten");
+                rewritten
+            }
+            josh::UnapplyResult::BranchDoesNotExist => {
+                return Err(josh::josh_error("branch does not exist on remote"));
+            }
+            josh::UnapplyResult::RejectMerge(conflicts) => {
+                return Err(josh::josh_error(&format!(
+                    "rejecting merge with conflicts in:\n{}",
+                    conflicts.join("\n")
+                )));
+            }
+            josh::UnapplyResult::RejectAmend(msg) => {
+                return Err(josh::josh_error(&format!(
+                    "rejecting to amend {:?} with conflicting changes",
+                    msg
+                )));
+            }
+        }
+    };
 
-            warning_str += "\nwarnings:";
-            for warn in warnings {
-                warning_str += "\n";
-                warning_str.push_str(&warn);
+    let backward_new_oid = if josh_rebase {
+        match josh::history::rebase(&transaction, filterobj, backward_new_oid, original_target)? {
+            Ok(rebased) => rebased,
+            Err(conflicts) => {
+                return Err(josh::josh_error(&format!(
+                    "rebase failed with conflicts in:\n{}",
+                    conflicts.join("\n")
+                )));
             }
         }
+    } else {
+        backward_new_oid
+    };
 
-        resp = format!("{}{}{}", resp, text, warning_str);
-
-        if new_oid != reapply {
-            transaction.repo().reference(
-                &format!(
-                    "refs/josh/rewrites/{}/{:?}/r_{}",
-                    repo_update.base_ns,
-                    filterobj.id(),
-                    reapply
-                ),
-                reapply,
-                true,
-                "reapply",
-            )?;
-            resp = format!("{}\nREWRITE({} -> {})", resp, new_oid, reapply);
-            tracing::debug!("REWRITE({} -> {})", new_oid, reapply);
+    let oid_to_push = if josh_merge {
+        let backward_commit = transaction.repo().find_commit(backward_new_oid)?;
+        if let Ok(Ok(base_commit)) = transaction
+            .repo()
+            .revparse_single(&original_target_ref)
+            .map(|x| x.peel_to_commit())
+        {
+            let merged_tree = transaction
+                .repo()
+                .merge_commits(&base_commit, &backward_commit, None)?
+                .write_tree_to(transaction.repo())?;
+            transaction.repo().commit(
+                None,
+                &backward_commit.author(),
+                &backward_commit.committer(),
+                &format!("Merge from {}", &repo_update.filter_spec),
+                &transaction.repo().find_tree(merged_tree)?,
+                &[&base_commit, &backward_commit],
+            )?
+        } else {
+            return Err(josh::josh_error("josh_merge failed"));
         }
+    } else {
+        backward_new_oid
+    };
 
-        if status == 0 {
-            return Ok(resp);
+    let push_with_options = if !options.is_empty() {
+        format!("{}{}{}", push_to, "%", options.join(","))
+    } else {
+        push_to
+    };
+
+    let reapply = josh::filter::apply_to_commit(
+        filterobj,
+        &transaction.repo().find_commit(oid_to_push)?,
+        &transaction,
+    )?;
+
+    let (text, status) = push_head_url(
+        transaction.repo(),
+        oid_to_push,
+        &push_with_options,
+        &repo_update.remote_url,
+        &repo_update.auth,
+        &repo_update.git_ns,
+    )?;
+
+    let warnings = josh::filter::compute_warnings(
+        &transaction,
+        filterobj,
+        transaction.repo().find_commit(oid_to_push)?.tree()?,
+    );
+
+    let mut warning_str = "".to_owned();
+    if !warnings.is_empty() {
+        let warnings = warnings.iter();
+
+        warning_str += "\nwarnings:";
+        for warn in warnings {
+            warning_str += "\n";
+            warning_str.push_str(warn);
         }
-        return Err(josh::josh_error(&resp));
     }
 
-    return Ok("".to_string());
+    let mut resp = format!("{}{}", text, warning_str);
+
+    if new_oid != reapply {
+        transaction.repo().reference(
+            &format!(
+                "refs/josh/rewrites/{}/{:?}/r_{}",
+                repo_update.base_ns,
+                filterobj.id(),
+                reapply
+            ),
+            reapply,
+            true,
+            "reapply",
+        )?;
+        resp = format!("{}\nREWRITE({} -> {})", resp, new_oid, reapply);
+        tracing::debug!("REWRITE({} -> {})", new_oid, reapply);
+    }
+
+    if status == 0 {
+        return Ok((resp, oid_to_push));
+    }
+    return Err(josh::josh_error(&resp));
+}
+
+/// Attach pushed notes to the original commits and push them upstream
+fn push_notes(
+    transaction: &josh::cache::Transaction,
+    repo_update: &RepoUpdate,
+    baseref: &str,
+    push_to: &str,
+    old: git2::Oid,
+    new: git2::Oid,
+) -> josh::JoshResult<(String, i32)> {
+    let filterobj = josh::filter::parse(&repo_update.filter_spec)?;
+    let repo = transaction.repo();
+
+    // Notes are updated on top of the upstream ones, so the push is a fast-forward
+    let upstream = repo.refname_to_id(&transaction.refname(baseref)).ok();
+
+    let heads: Vec<_> = repo
+        .references_glob(&transaction.refname("refs/heads/*"))?
+        .filter_map(|reference| reference.ok()?.target())
+        .collect();
+
+    let notes = josh::some_or!(
+        josh::history::unapply_notes(transaction, filterobj, upstream, old, new, &heads)?,
+        {
+            return Ok(("".to_string(), 0));
+        }
+    );
+
+    return push_head_url(
+        repo,
+        notes,
+        push_to,
+        &repo_update.remote_url,
+        &repo_update.auth,
+        &repo_update.git_ns,
+    );
 }
 
 fn push_head_url(
@@ -277,10 +452,11 @@ fn push_head_url(
         cwd: repo.path().to_owned(),
     };
     let (username, password) = auth.parse()?;
-    let nurl = url_with_auth(&url, &username);
-    let cmd = format!("git push {} '{}'", &nurl, &spec);
+    let nurl = url_with_auth(url, &username);
+    let cmd = ["git", "push", &nurl, &spec];
     let mut fakehead = repo.reference(&rn, oid, true, "push_head_url")?;
-    let (stdout, stderr, status) = shell.command_env(&cmd, &[], &[("GIT_PASSWORD", &password)]);
+    let (stdout, stderr, status) =
+        shell.command_args_env(&cmd, &[], &[("GIT_PASSWORD", &password)]);
     fakehead.delete()?;
     tracing::debug!("{}", &stderr);
     tracing::debug!("{}", &stdout);
@@ -290,6 +466,86 @@ fn push_head_url(
     return Ok((stderr, status));
 }
 
+/// Push `new`, a commit created by a GraphQL mutation on top of `old` in the filtered
+/// history, to the branch `refname` of the upstream repo. This goes through the same
+/// checks and mapping as a push to the proxy. Returns the pushed upstream commit.
+pub fn push_commit(
+    repo_update: &RepoUpdate,
+    refname: &str,
+    old: git2::Oid,
+    new: git2::Oid,
+) -> josh::JoshResult<git2::Oid> {
+    let (text, oid) = push_ref(
+        repo_update,
+        &std::collections::HashMap::new(),
+        refname,
+        &old.to_string(),
+        &new.to_string(),
+    )?;
+    tracing::debug!("push_commit: {}", text);
+    return Ok(oid);
+}
+
+/// Split a trailing `:depth=N` off a filter spec taken from an URL.
+/// The depth is not part of the filter itself, it gets resolved against the
+/// requested head by `depth_cutoff`.
+pub fn split_depth(filter_spec: &str) -> josh::JoshResult<(String, Option<usize>)> {
+    if let Some(i) = filter_spec.rfind(":depth=") {
+        let depth = filter_spec[i + ":depth=".len()..]
+            .parse::<usize>()
+            .map_err(|_| josh::josh_error("invalid depth"))?;
+        if depth == 0 {
+            return Err(josh::josh_error("depth must be at least 1"));
+        }
+        let rest = &filter_spec[..i];
+        let rest = if rest.is_empty() { ":/" } else { rest };
+        return Ok((rest.to_string(), Some(depth)));
+    }
+    return Ok((filter_spec.to_string(), None));
+}
+
+/// Restrict `filter` to the recent history on the first parent line of `tip`.
+/// The cutoff commit is remembered in `cutoff_ref` and kept while the branch grows, so
+/// fetches of a growing branch are fast-forwards most of the time. Once the view would
+/// get longer than `2 * depth - 1` commits it starts over with the last `depth` commits,
+/// and the cache of the previous view is dropped. Only the first `2 * depth` commits
+/// of the first parent line get looked at. Histories that are not longer than that
+/// are not restricted.
+pub fn depth_cutoff(
+    repo: &git2::Repository,
+    filter: josh::filter::Filter,
+    cutoff_ref: &str,
+    tip: git2::Oid,
+    depth: usize,
+) -> josh::JoshResult<josh::filter::Filter> {
+    let mut first_parents = vec![];
+    let mut commit = repo.find_commit(tip)?;
+    while first_parents.len() < 2 * depth {
+        first_parents.push(commit.id());
+        commit = josh::ok_or!(commit.parent(0), {
+            break;
+        });
+    }
+    if first_parents.len() < 2 * depth {
+        return Ok(filter);
+    }
+
+    let previous = repo.refname_to_id(cutoff_ref).ok();
+    if let Some(previous) = previous {
+        if first_parents[..2 * depth - 1].contains(&previous) {
+            return Ok(josh::filter::chain(josh::filter::since(previous), filter));
+        }
+    }
+
+    let cutoff = first_parents[depth - 1];
+    repo.reference(cutoff_ref, cutoff, true, "depth cutoff")?;
+    if let Some(previous) = previous {
+        josh::cache::drop_filter(josh::filter::chain(josh::filter::since(previous), filter))?;
+    }
+
+    return Ok(josh::filter::chain(josh::filter::since(cutoff), filter));
+}
+
 pub fn create_repo(path: &std::path::Path) -> josh::JoshResult<()> {
     tracing::debug!("init base repo: {:?}", path);
     std::fs::create_dir_all(path).expect("can't create_dir_all");
@@ -312,13 +568,12 @@ pub fn create_repo(path: &std::path::Path) -> josh::JoshResult<()> {
         .expect("can't symlink update hook");
     std::os::unix::fs::symlink(ce, path.join("hooks").join("pre-receive"))
         .expect("can't symlink pre-receive hook");
-    shell.command(&format!(
-        "git config credential.helper '!f() {{ echo \"password=\"$GIT_PASSWORD\"\"; }}; f'"
-    ));
-    shell.command(&"git config gc.auto 0");
-    shell.command(&"git pack-refs --all");
+    shell
+        .command("git config credential.helper '!f() { echo \"password=\"$GIT_PASSWORD\"\"; }; f'");
+    shell.command("git config gc.auto 0");
+    shell.command("git pack-refs --all");
 
-    if std::env::var_os("JOSH_KEEP_NS") == None {
+    if std::env::var_os("JOSH_KEEP_NS").is_none() {
         std::fs::remove_dir_all(path.join("refs/namespaces")).ok();
     }
     tracing::info!("repo initialized");
@@ -326,12 +581,12 @@ pub fn create_repo(path: &std::path::Path) -> josh::JoshResult<()> {
 }
 
 fn url_with_auth(url: &str, username: &str) -> String {
-    if username != "" {
+    if !username.is_empty() {
         let splitted: Vec<&str> = url.splitn(2, "://").collect();
         let proto = splitted[0];
         let rest = splitted[1];
         let username =
-            percent_encoding::utf8_percent_encode(&username, percent_encoding::NON_ALPHANUMERIC)
+            percent_encoding::utf8_percent_encode(username, percent_encoding::NON_ALPHANUMERIC)
                 .to_string();
         format!("{}://{}@{}", &proto, &username, &rest)
     } else {
@@ -353,7 +608,7 @@ pub fn fetch_refs_from_url(
         .iter()
         .map(|r| {
             format!(
-                "'+{}:refs/josh/upstream/{}/{}'",
+                "+{}:refs/josh/upstream/{}/{}",
                 &r,
                 josh::to_ns(upstream_repo),
                 &r
@@ -365,12 +620,13 @@ pub fn fetch_refs_from_url(
         cwd: path.to_owned(),
     };
     let (username, password) = auth.parse()?;
-    let nurl = url_with_auth(&url, &username);
+    let nurl = url_with_auth(url, &username);
 
-    let cmd = format!("git fetch --prune --no-tags {} {}", &nurl, &specs.join(" "));
+    let mut cmd = vec!["git", "fetch", "--prune", "--no-tags", &nurl];
+    cmd.extend(specs.iter().map(|x| x.as_str()));
     tracing::info!("fetch_refs_from_url {:?} {:?} {:?}", cmd, path, "");
 
-    let (_stdout, stderr, _) = shell.command_env(&cmd, &[], &[("GIT_PASSWORD", &password)]);
+    let (_stdout, stderr, _) = shell.command_args_env(&cmd, &[], &[("GIT_PASSWORD", &password)]);
     tracing::debug!("fetch_refs_from_url done {:?} {:?} {:?}", cmd, path, stderr);
     if stderr.contains("fatal: Authentication failed") {
         return Ok(false);
@@ -427,10 +683,15 @@ impl std::fmt::Debug for TmpGitNamespace {
 
 impl Drop for TmpGitNamespace {
     fn drop(&mut self) {
-        if std::env::var_os("JOSH_KEEP_NS") != None {
+        if std::env::var_os("JOSH_KEEP_NS").is_some() {
             return;
         }
+        std::fs::remove_file(push_options_path(&self.repo_path, &self.name)).ok();
         let request_tmp_namespace = self.repo_path.join("refs/namespaces").join(&self.name);
+        if !request_tmp_namespace.exists() {
+            // Nothing was filtered into the namespace
+            return;
+        }
         std::fs::remove_dir_all(&request_tmp_namespace).unwrap_or_else(|e| {
             tracing::error!(
                 "remove_dir_all {:?} failed, error:{:?}",
@@ -0,0 +1,291 @@
+//! Report filter progress to git clients as sideband messages, which they
+//! print with a `remote: ` prefix.
+
+use futures::StreamExt;
+
+/// Collects the progress of a filter run, to be sent to the client with `inject`.
+/// A streaming `Progress` sends the messages as sideband packets right away instead.
+#[derive(Default)]
+pub struct Progress {
+    messages: std::sync::Mutex<Vec<String>>,
+    stream: Option<tokio::sync::mpsc::UnboundedSender<Vec<u8>>>,
+}
+
+impl Progress {
+    pub fn new() -> Progress {
+        Progress::default()
+    }
+
+    pub fn streaming(stream: tokio::sync::mpsc::UnboundedSender<Vec<u8>>) -> Progress {
+        Progress {
+            messages: Default::default(),
+            stream: Some(stream),
+        }
+    }
+
+    pub fn take_messages(&self) -> Vec<String> {
+        std::mem::take(&mut *self.messages.lock().unwrap())
+    }
+
+    fn push(&self, msg: String) {
+        tracing::trace!("{}", msg.trim_end());
+        if let Some(stream) = &self.stream {
+            // Clients strip a trailing newline from the packets received ahead of the
+            // pack data, the next message would continue the line otherwise
+            let msg = if msg.ends_with('\n') {
+                format!("{}\n", msg)
+            } else {
+                msg
+            };
+            // The client going away is noticed by the request handler
+            stream.send(packet(2, msg.as_bytes())).ok();
+        } else {
+            self.messages.lock().unwrap().push(msg);
+        }
+    }
+}
+
+/// Lines ending in `\r` are overwritten by the next one on the client's terminal,
+/// like the progress output of git itself.
+fn line_end(n: usize, total: usize) -> &'static str {
+    if n >= total {
+        "\n"
+    } else {
+        "\r"
+    }
+}
+
+impl josh::cache::Progress for Progress {
+    fn walk(&self, filter: josh::filter::Filter, n_commits: usize, n_new: usize) {
+        self.push(format!(
+            "josh: filtering {}: {}/{} commits{}",
+            josh::filter::spec(filter),
+            n_commits,
+            n_new,
+            line_end(n_commits, n_new)
+        ));
+    }
+
+    fn refs(&self, n_refs: usize, total: usize) {
+        self.push(format!(
+            "josh: filtering refs: {}/{}{}",
+            n_refs,
+            total,
+            line_end(n_refs, total)
+        ));
+    }
+}
+
+/// Check if the client sending the upload-pack `request` shows progress messages.
+/// Compressed requests are not inspected and treated as not wanting progress.
+pub fn wants_progress(request: &[u8], content_encoding: Option<&str>) -> bool {
+    if content_encoding.unwrap_or("identity") != "identity" {
+        return false;
+    }
+    let contains = |needle: &[u8]| request.windows(needle.len()).any(|w| w == needle);
+    // Protocol v2 always uses sideband for the pack data of fetch responses
+    return (contains(b"side-band") || contains(b"command=fetch")) && !contains(b"no-progress");
+}
+
+/// Check if the client sending the upload-pack `request` asked for all of the response
+/// to be sent as sideband packets. Such responses can carry progress messages from the
+/// start, before any output of upload-pack is available.
+pub fn wants_sideband_all(request: &[u8]) -> bool {
+    return request
+        .windows(b"sideband-all".len())
+        .any(|w| w == b"sideband-all");
+}
+
+/// Encode `msg` as a packet on sideband channel `band`.
+pub fn packet(band: u8, msg: &[u8]) -> Vec<u8> {
+    let mut p = format!("{:04x}", msg.len() + 5).into_bytes();
+    p.push(band);
+    p.extend_from_slice(msg);
+    return p;
+}
+
+enum Scan {
+    /// The first sideband packet starts at the given offset
+    Found(usize),
+    NeedMore,
+    NotSideband,
+}
+
+/// Find the first packet carrying sideband data in the pkt-lines in `buf`.
+fn find_sideband(buf: &[u8]) -> Scan {
+    let mut pos = 0;
+    loop {
+        if buf.len() < pos + 4 {
+            return Scan::NeedMore;
+        }
+        let len = match std::str::from_utf8(&buf[pos..pos + 4])
+            .ok()
+            .and_then(|x| usize::from_str_radix(x, 16).ok())
+        {
+            Some(len) => len,
+            None => return Scan::NotSideband,
+        };
+        if len < 4 {
+            // flush, delimiter and response end packets
+            pos += 4;
+            continue;
+        }
+        if buf.len() < pos + 5 {
+            return Scan::NeedMore;
+        }
+        if (1..=3).contains(&buf[pos + 4]) {
+            return Scan::Found(pos);
+        }
+        pos += len;
+    }
+}
+
+/// Insert `messages` into an upload-pack response right before the pack data, where
+/// the client expects sideband packets. Responses without sideband data are passed on
+/// unchanged.
+pub fn inject(body: hyper::Body, messages: Vec<String>) -> hyper::Body {
+    if messages.is_empty() {
+        return body;
+    }
+
+    struct State {
+        body: hyper::Body,
+        buf: Vec<u8>,
+        messages: Option<Vec<String>>,
+    }
+
+    let state = State {
+        body,
+        buf: vec![],
+        messages: Some(messages),
+    };
+
+    hyper::Body::wrap_stream(futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.messages.is_none() {
+                if !state.buf.is_empty() {
+                    let chunk = hyper::body::Bytes::from(std::mem::take(&mut state.buf));
+                    return Some((Ok(chunk), state));
+                }
+                let chunk = state.body.next().await?;
+                return Some((chunk, state));
+            }
+
+            match find_sideband(&state.buf) {
+                Scan::Found(pos) => {
+                    let mut out = state.buf[..pos].to_vec();
+                    for msg in state.messages.take().unwrap_or_default() {
+                        out.extend(packet(2, msg.as_bytes()));
+                    }
+                    out.extend_from_slice(&state.buf[pos..]);
+                    state.buf = out;
+                }
+                Scan::NotSideband => state.messages = None,
+                Scan::NeedMore => match state.body.next().await {
+                    Some(Ok(chunk)) => state.buf.extend_from_slice(&chunk),
+                    Some(Err(e)) => return Some((Err(e), state)),
+                    None => state.messages = None,
+                },
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run(response: &'static [u8], messages: &[&str]) -> Vec<u8> {
+        let (mut sender, body) = hyper::Body::channel();
+        let injected = inject(body, messages.iter().map(|x| x.to_string()).collect());
+        tokio::spawn(async move {
+            // Deliver the response in small pieces to exercise the buffering
+            for chunk in response.chunks(3) {
+                sender
+                    .send_data(hyper::body::Bytes::from(chunk))
+                    .await
+                    .unwrap();
+            }
+        });
+        hyper::body::to_bytes(injected).await.unwrap().to_vec()
+    }
+
+    #[test]
+    fn packet_test() {
+        assert_eq!(b"000a\x02hello".to_vec(), packet(2, b"hello"));
+    }
+
+    #[test]
+    fn wants_progress_test() {
+        assert!(wants_progress(
+            b"0032want abc side-band-64k ofs-delta\n",
+            None
+        ));
+        assert!(!wants_progress(
+            b"0032want abc side-band-64k no-progress\n",
+            None
+        ));
+        assert!(!wants_progress(b"0032want abc ofs-delta\n", None));
+        assert!(wants_progress(
+            b"0012command=fetch\n0001000dofs-delta\n",
+            None
+        ));
+        assert!(!wants_progress(
+            b"0032want abc side-band-64k\n",
+            Some("gzip")
+        ));
+    }
+
+    #[test]
+    fn wants_sideband_all_test() {
+        assert!(wants_sideband_all(
+            b"0012command=fetch\n0001000dofs-delta\n0011sideband-all\n"
+        ));
+        assert!(!wants_sideband_all(
+            b"0012command=fetch\n0001000dofs-delta\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn streaming_test() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let progress = Progress::streaming(sender);
+        josh::cache::Progress::refs(&progress, 1, 2);
+        assert_eq!(
+            Some(b"001f\x02josh: filtering refs: 1/2\r".to_vec()),
+            receiver.recv().await
+        );
+        josh::cache::Progress::refs(&progress, 2, 2);
+        assert_eq!(
+            Some(b"0020\x02josh: filtering refs: 2/2\n\n".to_vec()),
+            receiver.recv().await
+        );
+        assert!(progress.take_messages().is_empty());
+    }
+
+    #[tokio::test]
+    async fn inject_test() {
+        assert_eq!(
+            b"0008NAK\n000a\x02hello0009\x01PACK0000".to_vec(),
+            run(b"0008NAK\n0009\x01PACK0000", &["hello"]).await
+        );
+        assert_eq!(
+            b"000dpackfile\n0006\x02a0006\x02b0009\x01PACK0000".to_vec(),
+            run(b"000dpackfile\n0009\x01PACK0000", &["a", "b"]).await
+        );
+
+        // Without sideband data the response is not changed
+        assert_eq!(
+            b"0008NAK\nPACK".to_vec(),
+            run(b"0008NAK\nPACK", &["hello"]).await
+        );
+        assert_eq!(
+            b"0031ACK 0000000000000000000000000000000000000000\n0000".to_vec(),
+            run(
+                b"0031ACK 0000000000000000000000000000000000000000\n0000",
+                &["hello"]
+            )
+            .await
+        );
+    }
+}
@@ -32,7 +32,7 @@ impl Component for Codemirror {
             .iter()
             .zip(self.props.marker_text.iter())
         {
-            set_marker(*pos, &text);
+            set_marker(*pos, text);
         }
     }
 
@@ -1,4 +1,5 @@
 #![recursion_limit = "10000"]
+#![allow(clippy::needless_return)]
 
 use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
@@ -53,7 +53,7 @@ impl AppRoute {
         let mut x = self.clone();
 
         loop {
-            if x.path != "" {
+            if !x.path.is_empty() {
                 r.push(x.clone());
             } else {
                 break;
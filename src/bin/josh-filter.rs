@@ -1,5 +1,6 @@
 #![deny(warnings)]
 #![warn(unused_extern_crates)]
+#![allow(clippy::needless_return)]
 
 #[macro_use]
 extern crate rs_tracing;
@@ -7,6 +8,30 @@ extern crate rs_tracing;
 use std::fs::read_to_string;
 use std::io::Write;
 
+struct ProgressBar;
+
+impl josh::cache::Progress for ProgressBar {
+    fn walk(&self, filter: josh::filter::Filter, n_commits: usize, n_new: usize) {
+        let width = 30;
+        let filled = if n_new == 0 {
+            width
+        } else {
+            std::cmp::min(width, n_commits * width / n_new)
+        };
+        eprint!(
+            "\r[{}{}] {}/{} {}",
+            "#".repeat(filled),
+            " ".repeat(width - filled),
+            n_commits,
+            n_new,
+            josh::filter::spec(filter)
+        );
+        if n_commits >= n_new {
+            eprintln!();
+        }
+    }
+}
+
 fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
     let args = clap::App::new("josh-filter")
         .arg(
@@ -66,9 +91,63 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
         )
         .arg(
             clap::Arg::with_name("pack")
-                .help("Write a packfile instead of loose objects")
+                .help("Write a packfile instead of loose objects (can't be combined with -j)")
                 .long("pack"),
         )
+        .arg(
+            clap::Arg::with_name("jobs")
+                .help("Number of threads used to filter independent histories")
+                .short("j")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("progress")
+                .help("Show a progress bar on stderr")
+                .long("progress"),
+        )
+        .arg(
+            clap::Arg::with_name("signatures")
+                .help("What to do with signatures of rewritten commits")
+                .long("signatures")
+                .possible_values(&["preserve", "strip", "resign"])
+                .default_value("preserve")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("signing-key")
+                .help("SSH key file used with --signatures=resign, runs ssh-keygen once per rewritten commit")
+                .long("signing-key")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("tag-message-template")
+                .help("Template for the messages of filtered annotated tags")
+                .long("tag-message-template")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("map")
+                .help("Write the commit mapping in git-filter-repo's commit-map format")
+                .long("map")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("map-changed-only")
+                .help("Only list commits in --map that changed the filtered tree")
+                .long("map-changed-only"),
+        )
+        .arg(
+            clap::Arg::with_name("evict")
+                .help("Drop the cache of filters that were not used for this many seconds")
+                .long("evict")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("explain")
+                .help("Explain why a commit is (or is not) part of the filtered history")
+                .long("explain")
+                .takes_value(true),
+        )
         .arg(
             clap::Arg::with_name("query")
                 .long("query")
@@ -114,11 +193,33 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
         return Ok(0);
     }
 
+    let jobs = args.value_of("jobs").unwrap_or("1").parse()?;
+    if jobs > 1 && args.is_present("pack") {
+        return Err(josh::josh_error("--pack can't be combined with -j"));
+    }
+
+    josh::history::set_signature_policy(match args.value_of("signatures") {
+        Some("strip") => josh::history::SignaturePolicy::Strip,
+        Some("resign") => josh::history::SignaturePolicy::Resign(
+            args.value_of("signing-key")
+                .ok_or(josh::josh_error("--signatures=resign needs --signing-key"))?
+                .into(),
+        ),
+        _ => josh::history::SignaturePolicy::Preserve,
+    });
+
     let repo = git2::Repository::open_from_env()?;
     if !args.is_present("no-cache") {
-        josh::cache::load(&repo.path())?;
+        josh::cache::load(repo.path())?;
     }
+    josh::cache::set_jobs(jobs);
+    josh::history::set_tag_message_template(
+        args.value_of("tag-message-template").map(String::from),
+    );
     let transaction = josh::cache::Transaction::new(repo, None);
+    if args.is_present("progress") {
+        transaction.set_progress(std::sync::Arc::new(ProgressBar));
+    }
     let repo = transaction.repo();
 
     let odb = repo.odb()?;
@@ -136,21 +237,61 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
         if args.is_present("cache-stats") {
             josh::cache::print_stats();
         }
+        if let Err(e) = josh::cache::flush() {
+            eprintln!("failed to write the cache: {}", e.0);
+        }
         if let Some(mempack) = mp {
             let mut buf = git2::Buf::new();
-            mempack.dump(&repo, &mut buf).unwrap();
+            mempack.dump(repo, &mut buf).unwrap();
             if buf.len() > 32 {
                 let mut w = odb.packwriter().unwrap();
-                w.write(&buf).unwrap();
+                w.write_all(&buf).unwrap();
                 w.commit().unwrap();
             }
         }
     });
 
+    if let Some(max_age) = args.value_of("evict") {
+        let max_age = std::time::Duration::from_secs(max_age.parse()?);
+        for spec in josh::cache::evict(max_age)? {
+            println!("evicted {}", spec);
+        }
+        return Ok(0);
+    }
+
     let input_ref = args.value_of("input").unwrap();
 
+    if let Some(rev) = args.value_of("explain") {
+        let commit = repo.revparse_single(rev)?.peel_to_commit()?;
+        let e = josh::history::explain(&transaction, filterobj, &commit)?;
+
+        println!("commit {}", commit.id());
+        if e.filtered == git2::Oid::zero() {
+            println!("not part of the filtered history");
+        } else if let Some(c) = e.decisions.collapsed_into {
+            println!("collapsed into {}", c);
+        } else {
+            println!("filtered to {}", e.filtered);
+        }
+        println!(
+            "filtered tree {}",
+            if e.decisions.tree_unchanged {
+                "unchanged"
+            } else {
+                "changed"
+            }
+        );
+        for f in e.touched_by {
+            println!("touched by {}", josh::filter::spec(f));
+        }
+        for p in e.decisions.dropped_parents {
+            println!("dropped parent {} (initial merge)", p);
+        }
+        return Ok(0);
+    }
+
     if args.is_present("discover") {
-        let r = repo.revparse_single(&input_ref)?;
+        let r = repo.revparse_single(input_ref)?;
         let hs = josh::housekeeping::find_all_workspaces_and_subdirectories(&r.peel_to_tree()?)?;
         for i in hs {
             if i.contains(":workspace=") {
@@ -183,7 +324,7 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
         target.to_string()
     };
     let src = repo
-        .revparse_ext(&src)?
+        .revparse_ext(src)?
         .1
         .ok_or(josh::josh_error("reference not found"))?
         .name()
@@ -192,6 +333,25 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
 
     josh::filter_refs(&transaction, filterobj, &[(src.clone(), t.clone())])?;
 
+    if let Some(map_file) = args.value_of("map") {
+        let map = josh::history::commit_map(
+            &transaction,
+            filterobj,
+            repo.refname_to_id(&src)?,
+            args.is_present("map-changed-only"),
+        )?;
+        let mut out = format!("{:<40} {}\n", "old", "new");
+        for (original, filtered) in map {
+            out.push_str(&format!("{} {}\n", original, filtered));
+        }
+        std::fs::write(map_file, out)?;
+    }
+
+    let invalidated = josh::history::invalidated_signatures();
+    if invalidated > 0 {
+        println!("Invalidated signatures: {}", invalidated);
+    }
+
     let mut all_paths = vec![];
 
     if check_permissions {
@@ -200,10 +360,7 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
         result_tree.walk(git2::TreeWalkMode::PreOrder, |_, entry| {
             let name = entry.name().unwrap();
             if name.starts_with("JOSH_ORIG_PATH_") {
-                let pathname = format!(
-                    "{}",
-                    josh::from_ns(&name.replacen("JOSH_ORIG_PATH_", "", 1))
-                );
+                let pathname = josh::from_ns(&name.replacen("JOSH_ORIG_PATH_", "", 1)).to_string();
                 all_paths.push(pathname);
             }
             git2::TreeWalkResult::Ok
@@ -231,10 +388,10 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
     if let Some(cp) = args.value_of("check-permission") {
         let pattern = glob::Pattern::new(cp)?;
 
-        let mut allowed = dedup.len() != 0;
+        let mut allowed = !dedup.is_empty();
         for d in dedup.iter() {
             let d = std::path::PathBuf::from(d);
-            let m = pattern.matches_path_with(&d, options.clone());
+            let m = pattern.matches_path_with(&d, options);
             if !m {
                 allowed = false;
                 println!("missing permission for: {:?}", &d);
@@ -244,9 +401,9 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
     }
 
     if reverse {
-        let new = repo.revparse_single(&target).unwrap().id();
+        let new = repo.revparse_single(target).unwrap().id();
         let old = repo.revparse_single("JOSH_TMP").unwrap().id();
-        let unfiltered_old = repo.revparse_single(&input_ref).unwrap().id();
+        let unfiltered_old = repo.revparse_single(input_ref).unwrap().id();
 
         match josh::history::unapply_filter(
             &transaction,
@@ -261,6 +418,10 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
             josh::UnapplyResult::Done(rewritten) => {
                 repo.reference(&src, rewritten, true, "unapply_filter")?;
             }
+            josh::UnapplyResult::RejectMerge(conflicts) => {
+                println!("conflicting paths:\n{}", conflicts.join("\n"));
+                return Ok(1);
+            }
             _ => {
                 return Ok(1);
             }
@@ -275,8 +436,8 @@ fn run_filter(args: Vec<String>) -> josh::JoshResult<i32> {
             josh::query::render(
                 &git2::Repository::open_from_env()?,
                 "",
-                &update_target.to_string(),
-                &query,
+                update_target,
+                query,
             )?
             .unwrap_or("File not found".to_string())
         );
@@ -295,10 +456,11 @@ fn main() {
         args
     };
 
-    std::process::exit(if let Err(e) = run_filter(args) {
-        println!("ERROR: {:?}", e);
-        1
-    } else {
-        0
+    std::process::exit(match run_filter(args) {
+        Ok(status) => status,
+        Err(e) => {
+            println!("ERROR: {:?}", e);
+            1
+        }
     })
 }
@@ -1,8 +1,26 @@
 use super::*;
 use std::collections::HashMap;
+use std::convert::TryInto;
 
+/// Version of the on-disk cache format. Bump this for every change to the format.
+/// If the existing data can be upgraded in place add a migration to `MIGRATIONS`,
+/// otherwise the cache will be rebuilt from scratch.
 const VERSION: u64 = 6;
 
+/// A migration upgrading the cache from the given version to the next one.
+type Migration = (u64, fn(&sled::Db) -> JoshResult<()>);
+
+/// Migrations upgrading the cache from the given version to the next one.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Access times of filters are written again when they are used after this many seconds,
+/// so filters used by long running transactions don't get evicted.
+const ACCESS_REFRESH_SECS: u64 = 60;
+
+/// Incremented whenever a cache tree is dropped. Handles to dropped trees stop working,
+/// so transactions reopen their trees when this changed.
+static TREE_GENERATION: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
 lazy_static! {
     static ref DB: std::sync::Mutex<Option<sled::Db>> = std::sync::Mutex::new(None);
     static ref REF_CACHE: std::sync::Mutex<HashMap<git2::Oid, HashMap<git2::Oid, git2::Oid>>> =
@@ -11,18 +29,431 @@ lazy_static! {
         std::sync::Mutex::new(HashMap::new());
     static ref GLOB_MAP: std::sync::Mutex<HashMap<(git2::Oid, git2::Oid), git2::Oid>> =
         std::sync::Mutex::new(HashMap::new());
+    static ref SEARCH_MAP: std::sync::Mutex<Lru<SearchKey, (SearchResults, bool)>> =
+        std::sync::Mutex::new(Lru::new(SEARCH_CACHE_SIZE));
+}
+
+/// Tree, search string and whether the search is a regex
+type SearchKey = (git2::Oid, String, bool);
+
+/// Lines matching a search in a tree, as (path, line number, text)
+pub type SearchResults = std::sync::Arc<Vec<(String, usize, String)>>;
+
+/// Number of searches kept in memory. Results of searches for common patterns can be
+/// large, so unlike the other in-memory caches this one is bounded.
+const SEARCH_CACHE_SIZE: usize = 256;
+
+/// A map keeping only the `capacity` most recently used entries
+struct Lru<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (u64, V)>,
+}
+
+impl<K: std::hash::Hash + Eq + Clone, V: Clone> Lru<K, V> {
+    fn new(capacity: usize) -> Lru<K, V> {
+        Lru {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+        return self.entries.get_mut(key).map(|(used, value)| {
+            *used = tick;
+            value.clone()
+        });
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.tick += 1;
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key, (self.tick, value));
+    }
+}
+
+static JOBS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(1);
+
+/// Set the number of threads used to filter independent histories and references.
+pub fn set_jobs(jobs: usize) {
+    JOBS.store(std::cmp::max(jobs, 1), std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn jobs() -> usize {
+    JOBS.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Number of worker threads started by `parallel` that are still running.
+static WORKERS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Reserve a slot for one more worker thread, keeping the total number of threads
+/// doing filter work, including the one calling `parallel`, at `jobs()`.
+fn reserve_worker() -> bool {
+    let mut n = WORKERS.load(std::sync::atomic::Ordering::SeqCst);
+    while n + 1 < jobs() {
+        match WORKERS.compare_exchange(
+            n,
+            n + 1,
+            std::sync::atomic::Ordering::SeqCst,
+            std::sync::atomic::Ordering::SeqCst,
+        ) {
+            Ok(_) => return true,
+            Err(current) => n = current,
+        }
+    }
+    return false;
+}
+
+fn release_worker() {
+    WORKERS.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+}
+
+type WorkQueue<T> = std::sync::Arc<std::sync::Mutex<Vec<T>>>;
+type WorkFn<T, R> = std::sync::Arc<dyn Fn(&Transaction, T) -> JoshResult<R> + Send + Sync>;
+
+/// Take items from `queue` and run `f` on them until the queue is empty, the transaction
+/// is cancelled or `f` failed on any thread.
+fn drain<T, R>(
+    transaction: &Transaction,
+    queue: &WorkQueue<T>,
+    failed: &std::sync::atomic::AtomicBool,
+    f: &(dyn Fn(&Transaction, T) -> JoshResult<R> + Send + Sync),
+) -> JoshResult<Vec<R>> {
+    let mut results = vec![];
+    while !failed.load(std::sync::atomic::Ordering::SeqCst) && !transaction.is_cancelled() {
+        let next = queue.lock()?.pop();
+        let item = some_or!(next, { break });
+        match f(transaction, item) {
+            Ok(r) => results.push(r),
+            Err(e) => {
+                failed.store(true, std::sync::atomic::Ordering::SeqCst);
+                return Err(e);
+            }
+        }
+    }
+    return Ok(results);
+}
+
+/// Run `f` on all `items`. The calling thread works on the items itself and is helped
+/// by as many worker threads, each with its own clone of `transaction`, as the `jobs()`
+/// limit allows. The limit is shared by all calls, so nested calls don't start more
+/// threads, they just do their work on the calling thread.
+/// Once `f` fails no further items are started, all workers are joined and the first
+/// error is returned.
+pub fn parallel<T, R, F>(transaction: &Transaction, items: Vec<T>, f: F) -> JoshResult<Vec<R>>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(&Transaction, T) -> JoshResult<R> + Send + Sync + 'static,
+{
+    let n_items = items.len();
+    let queue: WorkQueue<T> =
+        std::sync::Arc::new(std::sync::Mutex::new(items.into_iter().rev().collect()));
+    let failed = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let f: WorkFn<T, R> = std::sync::Arc::new(f);
+
+    let mut workers = vec![];
+    while workers.len() + 1 < n_items && reserve_worker() {
+        let t = match transaction.clone() {
+            Ok(t) => t,
+            Err(e) => {
+                release_worker();
+                failed.store(true, std::sync::atomic::Ordering::SeqCst);
+                join_workers(transaction, workers)?;
+                return Err(e);
+            }
+        };
+        let queue = queue.clone();
+        let failed = failed.clone();
+        let f = f.clone();
+        let span = tracing::Span::current();
+        workers.push(std::thread::spawn(move || {
+            let _e = span.enter();
+            let _release = defer::defer(release_worker);
+            let r = drain(&t, &queue, &failed, f.as_ref());
+            (r, t)
+        }));
+    }
+
+    let r = drain(transaction, &queue, &failed, f.as_ref());
+    let joined = join_workers(transaction, workers);
+    let mut results = r?;
+    results.extend(joined?);
+    return Ok(results);
+}
+
+fn join_workers<R>(
+    transaction: &Transaction,
+    workers: Vec<std::thread::JoinHandle<(JoshResult<Vec<R>>, Transaction)>>,
+) -> JoshResult<Vec<R>> {
+    let mut results = Ok(vec![]);
+    for worker in workers {
+        match worker.join() {
+            Ok((r, t)) => {
+                transaction.merge_walk_state(&t);
+                match (&mut results, r) {
+                    (Ok(results), Ok(r)) => results.extend(r),
+                    (Ok(_), Err(e)) => results = Err(e),
+                    _ => {}
+                }
+            }
+            Err(_) => {
+                if results.is_ok() {
+                    results = Err(josh_error("worker thread panicked"));
+                }
+            }
+        }
+    }
+    return results;
 }
 
 pub fn load(path: &std::path::Path) -> JoshResult<()> {
-    *DB.lock()? = Some(
-        sled::Config::default()
-            .path(path.join(format!("josh/{}/sled/", VERSION)))
-            .flush_every_ms(Some(200))
-            .open()?,
-    );
+    let josh_dir = path.join("josh");
+    let cache_dir = josh_dir.join("cache").join("sled");
+
+    // Caches created before the version was recorded inside of the database live in
+    // a directory named after their version. Adopt the newest one of those.
+    let legacy_version = if cache_dir.exists() {
+        None
+    } else if let Some((version, legacy_dir)) = find_legacy_dir(&josh_dir)? {
+        std::fs::create_dir_all(josh_dir.join("cache"))?;
+        std::fs::rename(legacy_dir, &cache_dir)?;
+        Some(version)
+    } else {
+        None
+    };
+
+    let db = open_db(&cache_dir)?;
+    let version = read_version(&db)?.or(legacy_version).unwrap_or(VERSION);
+
+    if version > VERSION {
+        return Err(josh_error(&format!(
+            "cache in {:?} was written by a newer version ({} > {}), refusing to open it",
+            cache_dir, version, VERSION
+        )));
+    }
+
+    let db = match migrate(&db, version, VERSION, MIGRATIONS) {
+        Ok(true) => db,
+        r => {
+            log::warn!(
+                "can't migrate cache from version {} to {}: {:?}",
+                version,
+                VERSION,
+                r
+            );
+            std::mem::drop(db);
+            std::fs::remove_dir_all(&cache_dir)?;
+            open_db(&cache_dir)?
+        }
+    };
+    write_version(&db, VERSION)?;
+    db.flush()?;
+
+    remove_legacy_dirs(&josh_dir)?;
+
+    *DB.lock()? = Some(db);
+    Ok(())
+}
+
+fn open_db(path: &std::path::Path) -> JoshResult<sled::Db> {
+    Ok(sled::Config::default()
+        .path(path)
+        .flush_every_ms(Some(200))
+        .open()?)
+}
+
+fn read_version(db: &sled::Db) -> JoshResult<Option<u64>> {
+    if let Some(v) = db.open_tree("_meta")?.get("version")? {
+        return Ok(Some(u64::from_be_bytes(v.as_ref().try_into()?)));
+    }
+    return Ok(None);
+}
+
+fn write_version(db: &sled::Db, version: u64) -> JoshResult<()> {
+    db.open_tree("_meta")?
+        .insert("version", version.to_be_bytes().to_vec())?;
+    Ok(())
+}
+
+/// Upgrade the cache in place from `version` to `target`.
+/// Returns `false` if there is no migration path.
+fn migrate(db: &sled::Db, version: u64, target: u64, migrations: &[Migration]) -> JoshResult<bool> {
+    let mut version = version;
+    while version < target {
+        let (_, migration) = some_or!(migrations.iter().find(|(from, _)| *from == version), {
+            return Ok(false);
+        });
+        log::info!(
+            "migrating cache from version {} to {}",
+            version,
+            version + 1
+        );
+        migration(db)?;
+        version += 1;
+        write_version(db, version)?;
+    }
+    return Ok(version == target);
+}
+
+fn legacy_dirs(josh_dir: &std::path::Path) -> JoshResult<Vec<(u64, std::path::PathBuf)>> {
+    let mut dirs = vec![];
+    if !josh_dir.exists() {
+        return Ok(dirs);
+    }
+    for entry in std::fs::read_dir(josh_dir)? {
+        let entry = entry?;
+        if let Some(Ok(version)) = entry.file_name().to_str().map(|x| x.parse::<u64>()) {
+            dirs.push((version, entry.path()));
+        }
+    }
+    dirs.sort();
+    return Ok(dirs);
+}
+
+fn find_legacy_dir(josh_dir: &std::path::Path) -> JoshResult<Option<(u64, std::path::PathBuf)>> {
+    return Ok(legacy_dirs(josh_dir)?
+        .into_iter()
+        .filter(|(version, dir)| *version <= VERSION && dir.join("sled").exists())
+        .map(|(version, dir)| (version, dir.join("sled")))
+        .last());
+}
+
+/// Remove caches of older versions. Directories of newer versions are left alone,
+/// they may still be in use by a newer josh sharing the same repository.
+fn remove_legacy_dirs(josh_dir: &std::path::Path) -> JoshResult<()> {
+    for (version, dir) in legacy_dirs(josh_dir)? {
+        if version >= VERSION {
+            continue;
+        }
+        log::info!("removing cache of version {}: {:?}", version, dir);
+        std::fs::remove_dir_all(dir)?;
+    }
     Ok(())
 }
 
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Name of the cache tree of `filter`. Non-default signature policies change the ids of
+/// filtered commits, so the results of filtering with them are kept in separate trees.
+fn tree_name(filter: filter::Filter) -> String {
+    let spec = filter::spec(filter);
+    return match history::signature_policy_key() {
+        Some(key) => format!("{}\n{}", spec, key),
+        None => spec,
+    };
+}
+
+/// The filter spec part of a cache tree name
+fn tree_spec(name: &str) -> &str {
+    return name.split('\n').next().unwrap_or("");
+}
+
+/// Drop the cached data of `filter`, for all signature policies.
+/// Returns `true` if there was a cache tree for the filter.
+pub fn drop_filter(filter: filter::Filter) -> JoshResult<bool> {
+    REF_CACHE.lock()?.remove(&filter.id());
+    let spec = filter::spec(filter);
+    let names = {
+        let d = DB.lock()?;
+        let db = d.as_ref().ok_or(josh_error("cache not loaded"))?;
+        db.tree_names()
+            .into_iter()
+            .map(|name| String::from_utf8_lossy(&name).to_string())
+            .filter(|name| tree_spec(name) == spec)
+            .collect::<Vec<_>>()
+    };
+    let mut dropped = false;
+    for name in names.iter() {
+        dropped |= drop_tree(name)?;
+    }
+    Ok(dropped)
+}
+
+fn drop_tree(name: &str) -> JoshResult<bool> {
+    let d = DB.lock()?;
+    let db = d.as_ref().ok_or(josh_error("cache not loaded"))?;
+    db.open_tree("_access")?.remove(name.as_bytes())?;
+    if let Ok(filter) = filter::parse(tree_spec(name)) {
+        let reverse_tree = db.open_tree("_reverse")?;
+        for key in reverse_tree.scan_prefix(filter.id().as_bytes()).keys() {
+            reverse_tree.remove(key?)?;
+        }
+    }
+    let dropped = db.drop_tree(name.as_bytes())?;
+    TREE_GENERATION.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    Ok(dropped)
+}
+
+/// Write all pending changes of the cache to disk. Short lived processes need to call
+/// this before they exit, as the cache is never dropped.
+pub fn flush() -> JoshResult<()> {
+    if let Some(db) = DB.lock()?.as_ref() {
+        db.flush()?;
+    }
+    Ok(())
+}
+
+/// Drop the cached data of all filters that have not been accessed within `max_age`
+/// and return their specs.
+/// Filters without a recorded access time (cached before access tracking existed or
+/// only built by housekeeping) are treated as accessed on the first call, so they
+/// get evicted `max_age` later unless they are used.
+pub fn evict(max_age: std::time::Duration) -> JoshResult<Vec<String>> {
+    let cutoff = now_secs().saturating_sub(max_age.as_secs());
+    let expired = {
+        let d = DB.lock()?;
+        let db = d.as_ref().ok_or(josh_error("cache not loaded"))?;
+        let access_tree = db.open_tree("_access")?;
+        for name in db.tree_names() {
+            if name.starts_with(b"_") || access_tree.contains_key(&name)? {
+                continue;
+            }
+            if filter::parse(tree_spec(&String::from_utf8_lossy(&name))).is_ok() {
+                access_tree.insert(&name, now_secs().to_be_bytes().to_vec())?;
+            }
+        }
+        let mut expired = vec![];
+        for entry in access_tree.iter() {
+            let (name, time) = entry?;
+            let time = u64::from_be_bytes(time.as_ref().try_into()?);
+            if time < cutoff {
+                expired.push(String::from_utf8(name.to_vec())?);
+            }
+        }
+        expired
+    };
+
+    let mut specs = vec![];
+    for name in expired.iter() {
+        if let Ok(filter) = filter::parse(tree_spec(name)) {
+            REF_CACHE.lock()?.remove(&filter.id());
+        }
+        drop_tree(name)?;
+        if !specs.iter().any(|spec| spec == tree_spec(name)) {
+            specs.push(tree_spec(name).to_string());
+        }
+    }
+    Ok(specs)
+}
+
 pub fn print_stats() {
     let d = DB.lock().unwrap();
     let db = d.as_ref().unwrap();
@@ -31,14 +462,19 @@ pub fn print_stats() {
     let mut v = vec![];
     for name in db.tree_names() {
         let name = String::from_utf8(name.to_vec()).unwrap();
+        if name == "_access" || name == "_meta" || name == "_reverse" {
+            continue;
+        }
         let t = db.open_tree(&name).unwrap();
-        if t.len() != 0 {
-            let name = if name.contains("SUBTRACT") {
-                name.clone()
-            } else if name.starts_with("_") {
+        if !t.is_empty() {
+            let name = if name.contains("SUBTRACT") || name.starts_with('_') {
                 name.clone()
             } else {
-                filter::pretty(filter::parse(&name).unwrap(), 4)
+                let pretty = filter::pretty(filter::parse(tree_spec(&name)).unwrap(), 4);
+                match name.split_once('\n') {
+                    Some((_, policy)) => format!("{} ({})", pretty, policy),
+                    None => pretty,
+                }
             };
             v.push((t.len(), name));
         }
@@ -51,7 +487,34 @@ pub fn print_stats() {
     }
 }
 
-#[allow(unused)]
+/// Observer for the progress of long running filter operations.
+pub trait Progress: Send + Sync {
+    /// `n_commits` out of `n_new` commits have been filtered with `filter`.
+    fn walk(&self, _filter: filter::Filter, _n_commits: usize, _n_new: usize) {}
+
+    /// `n_refs` out of `total` references have been filtered.
+    fn refs(&self, _n_refs: usize, _total: usize) {}
+}
+
+/// Token to abort filter operations from another thread.
+#[derive(Clone, Default)]
+pub struct Cancellation(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+impl Cancellation {
+    pub fn new() -> Cancellation {
+        Cancellation::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+/// Caches shared between a transaction and all of its clones.
 struct Transaction2 {
     commit_map: HashMap<git2::Oid, HashMap<git2::Oid, git2::Oid>>,
     apply_map: HashMap<git2::Oid, HashMap<git2::Oid, git2::Oid>>,
@@ -59,27 +522,79 @@ struct Transaction2 {
     sled_trees: HashMap<git2::Oid, sled::Tree>,
     path_tree: sled::Tree,
     invert_tree: sled::Tree,
+    access_tree: sled::Tree,
+    reverse_tree: sled::Tree,
+    generation: usize,
+    tracked: HashMap<git2::Oid, u64>,
+    progress: Option<std::sync::Arc<dyn Progress>>,
+    cancellation: Cancellation,
+}
+
+impl Transaction2 {
+    /// Open the cache tree of `filter`. The access time of the filter is recorded when
+    /// a handle with `track_access` set uses it, at most every `ACCESS_REFRESH_SECS`.
+    fn filter_tree(
+        &mut self,
+        filter: filter::Filter,
+        track_access: bool,
+    ) -> JoshResult<sled::Tree> {
+        let generation = TREE_GENERATION.load(std::sync::atomic::Ordering::SeqCst);
+        if generation != self.generation {
+            // Trees were dropped since they were opened, the old handles are unusable
+            self.sled_trees.clear();
+            self.tracked.clear();
+            self.generation = generation;
+        }
+        if track_access {
+            let now = now_secs();
+            let recorded = self.tracked.get(&filter.id()).cloned();
+            if recorded
+                .map(|t| now >= t + ACCESS_REFRESH_SECS)
+                .unwrap_or(true)
+            {
+                self.access_tree
+                    .insert(tree_name(filter).as_bytes(), now.to_be_bytes().to_vec())?;
+                self.tracked.insert(filter.id(), now);
+            }
+        }
+        if let Some(tree) = self.sled_trees.get(&filter.id()) {
+            return Ok(tree.clone());
+        }
+        let tree = DB
+            .lock()?
+            .as_ref()
+            .ok_or(josh_error("cache not loaded"))?
+            .open_tree(tree_name(filter))?;
+        self.sled_trees.insert(filter.id(), tree.clone());
+        Ok(tree)
+    }
+}
+
+/// State of the history walks done through one transaction handle.
+/// This is not shared with clones: Every thread walks on its own, workers hand their
+/// statistics back with `merge_walk_state` when they are done.
+struct WalkState {
     missing: Vec<(filter::Filter, git2::Oid)>,
     misses: usize,
     walks: usize,
 }
 
+/// A handle to the repository and the caches used for filtering.
+/// `git2::Repository` can't be shared between threads, so every worker thread needs
+/// its own handle. Those are created with `clone()` and share all caches with the
+/// handle they were cloned from.
 pub struct Transaction {
-    t2: std::cell::RefCell<Transaction2>,
+    t2: std::sync::Arc<std::sync::Mutex<Transaction2>>,
+    walk: std::cell::RefCell<WalkState>,
+    track_access: std::cell::Cell<bool>,
+    explain: std::cell::RefCell<Option<(git2::Oid, Option<history::Decisions>)>>,
     repo: git2::Repository,
     ref_prefix: String,
 }
 
 impl Transaction {
     pub fn open(path: &std::path::Path, ref_prefix: Option<&str>) -> JoshResult<Transaction> {
-        Ok(Transaction::new(
-            git2::Repository::open_ext(
-                path,
-                git2::RepositoryOpenFlags::NO_SEARCH,
-                &[] as &[&std::ffi::OsStr],
-            )?,
-            ref_prefix,
-        ))
+        Ok(Transaction::new(open_repo(path)?, ref_prefix))
     }
 
     pub fn status(&self, _msg: &str) {
@@ -104,25 +619,109 @@ impl Transaction {
             .unwrap()
             .open_tree("_invert")
             .unwrap();
-        Transaction {
-            t2: std::cell::RefCell::new(Transaction2 {
+        let access_tree = DB
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .open_tree("_access")
+            .unwrap();
+        let reverse_tree = DB
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .open_tree("_reverse")
+            .unwrap();
+        Transaction::with_caches(
+            std::sync::Arc::new(std::sync::Mutex::new(Transaction2 {
                 commit_map: HashMap::new(),
                 apply_map: HashMap::new(),
                 unapply_map: HashMap::new(),
                 sled_trees: HashMap::new(),
                 path_tree,
                 invert_tree,
+                access_tree,
+                reverse_tree,
+                generation: TREE_GENERATION.load(std::sync::atomic::Ordering::SeqCst),
+                tracked: HashMap::new(),
+                progress: None,
+                cancellation: Cancellation::new(),
+            })),
+            repo,
+            ref_prefix.unwrap_or(""),
+            true,
+        )
+    }
+
+    fn with_caches(
+        t2: std::sync::Arc<std::sync::Mutex<Transaction2>>,
+        repo: git2::Repository,
+        ref_prefix: &str,
+        track_access: bool,
+    ) -> Transaction {
+        Transaction {
+            t2,
+            walk: std::cell::RefCell::new(WalkState {
                 missing: vec![],
                 misses: 0,
                 walks: 0,
             }),
+            track_access: std::cell::Cell::new(track_access),
+            explain: std::cell::RefCell::new(None),
             repo,
-            ref_prefix: ref_prefix.unwrap_or("").to_string(),
+            ref_prefix: ref_prefix.to_string(),
         }
     }
 
+    /// Create a new handle sharing the caches of this one, to be used from another thread.
+    /// The new handle starts with an empty walk state and inherits `track_access`.
+    #[allow(clippy::should_implement_trait)]
     pub fn clone(&self) -> JoshResult<Transaction> {
-        Transaction::open(self.repo.path(), Some(&self.ref_prefix))
+        Ok(Transaction::with_caches(
+            self.t2.clone(),
+            open_repo(self.repo.path())?,
+            &self.ref_prefix,
+            self.track_access.get(),
+        ))
+    }
+
+    /// Add the cache misses counted by `other`, a finished worker clone of this handle.
+    pub fn merge_walk_state(&self, other: &Transaction) {
+        self.walk.borrow_mut().misses += other.misses();
+    }
+
+    /// Record the time filters are used at so `evict` can drop the unused ones.
+    /// Housekeeping disables this to not keep filters alive that nobody requests.
+    /// This only applies to this handle and clones created from it afterwards.
+    pub fn set_track_access(&self, track_access: bool) {
+        self.track_access.set(track_access);
+    }
+
+    /// Report the progress of filter operations done with this transaction and its clones.
+    pub fn set_progress(&self, progress: std::sync::Arc<dyn Progress>) {
+        self.t2.lock().unwrap().progress = Some(progress);
+    }
+
+    pub fn progress(&self) -> Option<std::sync::Arc<dyn Progress>> {
+        self.t2.lock().unwrap().progress.clone()
+    }
+
+    /// Make filter operations done with this transaction and its clones fail
+    /// once `cancellation` is cancelled.
+    pub fn set_cancellation(&self, cancellation: Cancellation) {
+        self.t2.lock().unwrap().cancellation = cancellation;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.t2.lock().unwrap().cancellation.is_cancelled()
+    }
+
+    pub fn check_cancelled(&self) -> JoshResult<()> {
+        if self.is_cancelled() {
+            return Err(josh_error("cancelled"));
+        }
+        Ok(())
     }
 
     pub fn repo(&self) -> &git2::Repository {
@@ -134,29 +733,29 @@ impl Transaction {
     }
 
     pub fn misses(&self) -> usize {
-        self.t2.borrow().misses
+        self.walk.borrow().misses
     }
 
     pub fn new_walk(&self) -> usize {
-        let prev = self.t2.borrow().walks;
-        self.t2.borrow_mut().walks += 1;
+        let prev = self.walk.borrow().walks;
+        self.walk.borrow_mut().walks += 1;
         return prev;
     }
 
     pub fn end_walk(&self) {
-        self.t2.borrow_mut().walks -= 1;
+        self.walk.borrow_mut().walks -= 1;
     }
 
     pub fn insert_apply(&self, filter: filter::Filter, from: git2::Oid, to: git2::Oid) {
-        let mut t2 = self.t2.borrow_mut();
+        let mut t2 = self.t2.lock().unwrap();
         t2.apply_map
             .entry(filter.id())
-            .or_insert_with(|| HashMap::new())
+            .or_default()
             .insert(from, to);
     }
 
     pub fn get_apply(&self, filter: filter::Filter, from: git2::Oid) -> Option<git2::Oid> {
-        let t2 = self.t2.borrow_mut();
+        let t2 = self.t2.lock().unwrap();
         if let Some(m) = t2.apply_map.get(&filter.id()) {
             return m.get(&from).cloned();
         }
@@ -164,48 +763,44 @@ impl Transaction {
     }
 
     pub fn insert_unapply(&self, filter: filter::Filter, from: git2::Oid, to: git2::Oid) {
-        let mut t2 = self.t2.borrow_mut();
+        let mut t2 = self.t2.lock().unwrap();
         t2.unapply_map
             .entry(filter.id())
-            .or_insert_with(|| HashMap::new())
+            .or_default()
             .insert(from, to);
     }
 
     pub fn insert_paths(&self, tree: (git2::Oid, String), result: git2::Oid) {
-        let t2 = self.t2.borrow();
+        let path_tree = self.t2.lock().unwrap().path_tree.clone();
         let s = format!("{:?}", tree);
         let x = git2::Oid::hash_object(git2::ObjectType::Blob, s.as_bytes()).expect("hash_object");
-        t2.path_tree
-            .insert(x.as_bytes(), result.as_bytes())
-            .unwrap();
+        path_tree.insert(x.as_bytes(), result.as_bytes()).unwrap();
     }
 
     pub fn get_paths(&self, tree: (git2::Oid, String)) -> Option<git2::Oid> {
-        let t2 = self.t2.borrow();
+        let path_tree = self.t2.lock().unwrap().path_tree.clone();
         let s = format!("{:?}", tree);
         let x = git2::Oid::hash_object(git2::ObjectType::Blob, s.as_bytes()).expect("hash_object");
 
-        if let Some(oid) = t2.path_tree.get(x.as_bytes()).unwrap() {
+        if let Some(oid) = path_tree.get(x.as_bytes()).unwrap() {
             return Some(git2::Oid::from_bytes(&oid).unwrap());
         }
         return None;
     }
 
     pub fn insert_invert(&self, tree: (git2::Oid, String), result: git2::Oid) {
-        let t2 = self.t2.borrow();
+        let invert_tree = self.t2.lock().unwrap().invert_tree.clone();
         let s = format!("{:?}", tree);
         let x = git2::Oid::hash_object(git2::ObjectType::Blob, s.as_bytes()).expect("hash_object");
-        t2.invert_tree
-            .insert(x.as_bytes(), result.as_bytes())
-            .unwrap();
+        invert_tree.insert(x.as_bytes(), result.as_bytes()).unwrap();
     }
 
     pub fn get_invert(&self, tree: (git2::Oid, String)) -> Option<git2::Oid> {
-        let t2 = self.t2.borrow();
+        let invert_tree = self.t2.lock().unwrap().invert_tree.clone();
         let s = format!("{:?}", tree);
         let x = git2::Oid::hash_object(git2::ObjectType::Blob, s.as_bytes()).expect("hash_object");
 
-        if let Some(oid) = t2.invert_tree.get(x.as_bytes()).unwrap() {
+        if let Some(oid) = invert_tree.get(x.as_bytes()).unwrap() {
             return Some(git2::Oid::from_bytes(&oid).unwrap());
         }
         return None;
@@ -227,12 +822,26 @@ impl Transaction {
         return GLOB_MAP.lock().unwrap().get(&tree).cloned();
     }
 
+    /// Cache the results of a search, `complete` is false if it stopped early.
+    pub fn insert_search(
+        &self,
+        key: (git2::Oid, String, bool),
+        result: SearchResults,
+        complete: bool,
+    ) {
+        SEARCH_MAP.lock().unwrap().insert(key, (result, complete));
+    }
+
+    pub fn get_search(&self, key: &(git2::Oid, String, bool)) -> Option<(SearchResults, bool)> {
+        return SEARCH_MAP.lock().unwrap().get(key);
+    }
+
     pub fn insert_ref(&self, filter: filter::Filter, from: git2::Oid, to: git2::Oid) {
         REF_CACHE
             .lock()
             .unwrap()
             .entry(filter.id())
-            .or_insert_with(|| HashMap::new())
+            .or_default()
             .insert(from, to);
     }
 
@@ -248,104 +857,346 @@ impl Transaction {
     }
 
     pub fn get_unapply(&self, filter: filter::Filter, from: git2::Oid) -> Option<git2::Oid> {
-        let t2 = self.t2.borrow_mut();
+        let t2 = self.t2.lock().unwrap();
         if let Some(m) = t2.unapply_map.get(&filter.id()) {
             return m.get(&from).cloned();
         }
         return None;
     }
 
-    pub fn insert(&self, filter: filter::Filter, from: git2::Oid, to: git2::Oid, store: bool) {
-        let mut t2 = self.t2.borrow_mut();
+    pub fn insert(
+        &self,
+        filter: filter::Filter,
+        from: git2::Oid,
+        to: git2::Oid,
+        store: bool,
+    ) -> JoshResult<()> {
+        let mut t2 = self.t2.lock()?;
         t2.commit_map
             .entry(filter.id())
-            .or_insert_with(|| HashMap::new())
+            .or_insert_with(HashMap::new)
             .insert(from, to);
 
         // In addition to commits that are explicitly requested to be stored, also store
         // random extra commits (probability 1/256) to avoid long searches for filters that reduce
         // the history length by a very large factor.
         if store || from.as_bytes()[0] == 0 {
-            let t = t2.sled_trees.entry(filter.id()).or_insert_with(|| {
-                DB.lock()
-                    .unwrap()
-                    .as_ref()
-                    .unwrap()
-                    .open_tree(filter::spec(filter))
-                    .unwrap()
-            });
-
-            t.insert(from.as_bytes(), to.as_bytes()).unwrap();
-        }
-    }
-    pub fn len(&self, filter: filter::Filter) -> usize {
-        let mut t2 = self.t2.borrow_mut();
-        let t = t2.sled_trees.entry(filter.id()).or_insert_with(|| {
-            DB.lock()
-                .unwrap()
-                .as_ref()
-                .unwrap()
-                .open_tree(filter::spec(filter))
-                .unwrap()
-        });
+            let t = t2.filter_tree(filter, self.track_access.get())?;
+            let reverse_tree = t2.reverse_tree.clone();
+            std::mem::drop(t2);
 
-        return t.len();
+            t.insert(from.as_bytes(), to.as_bytes())?;
+
+            // Explicitly stored commits are the ones that introduce a new filtered commit,
+            // so they are the ones we are looking for when searching the original of
+            // a filtered commit.
+            if store && to != git2::Oid::zero() {
+                reverse_tree.insert(
+                    [filter.id().as_bytes(), to.as_bytes(), from.as_bytes()].concat(),
+                    Vec::<u8>::new(),
+                )?;
+            }
+        }
+        Ok(())
     }
 
-    pub fn get_missing(&self) -> Vec<(filter::Filter, git2::Oid)> {
-        let mut missing = self.t2.borrow().missing.clone();
+    /// Original commits known to have been filtered to `to` with `filter`.
+    pub fn get_originals(&self, filter: filter::Filter, to: git2::Oid) -> Vec<git2::Oid> {
+        let reverse_tree = self.t2.lock().unwrap().reverse_tree.clone();
+        let prefix = [filter.id().as_bytes(), to.as_bytes()].concat();
+        reverse_tree
+            .scan_prefix(&prefix)
+            .keys()
+            .filter_map(|k| k.ok())
+            .filter_map(|k| git2::Oid::from_bytes(&k[prefix.len()..]).ok())
+            .collect()
+    }
+
+    pub fn len(&self, filter: filter::Filter) -> JoshResult<usize> {
+        let t = self
+            .t2
+            .lock()?
+            .filter_tree(filter, self.track_access.get())?;
+
+        return Ok(t.len());
+    }
+
+    pub fn get_missing(&self) -> JoshResult<Vec<(filter::Filter, git2::Oid)>> {
+        let mut missing = vec![];
+        for (f, i) in self.walk.borrow().missing.iter() {
+            if !self.known(*f, *i)? {
+                missing.push((*f, *i));
+            }
+        }
         missing.sort();
         missing.dedup();
-        missing.retain(|(f, i)| !self.known(*f, *i));
-        self.t2.borrow_mut().missing = missing.clone();
-        return missing;
+        self.walk.borrow_mut().missing = missing.clone();
+        return Ok(missing);
     }
 
-    pub fn known(&self, filter: filter::Filter, from: git2::Oid) -> bool {
-        self.get2(filter, from).is_some()
+    pub fn known(&self, filter: filter::Filter, from: git2::Oid) -> JoshResult<bool> {
+        Ok(self.get2(filter, from)?.is_some())
     }
 
-    pub fn get(&self, filter: filter::Filter, from: git2::Oid) -> Option<git2::Oid> {
-        if let Some(x) = self.get2(filter, from) {
-            return Some(x);
+    /// Record the decisions taken by `history::create_filtered_commit` for `commit`
+    /// until `finish_explain` is called. While recording, cached results for `commit` are
+    /// ignored so it gets filtered again.
+    pub fn start_explain(&self, commit: git2::Oid) {
+        *self.explain.borrow_mut() = Some((commit, None));
+    }
+
+    /// Stop recording and return the decisions of the outermost filter, if any of the
+    /// filtering done for the commit went through `history::create_filtered_commit`.
+    pub fn finish_explain(&self) -> Option<history::Decisions> {
+        return self.explain.borrow_mut().take().and_then(|(_, d)| d);
+    }
+
+    pub fn is_explaining(&self, commit: git2::Oid) -> bool {
+        return matches!(&*self.explain.borrow(), Some((c, _)) if *c == commit);
+    }
+
+    pub fn record_decisions(&self, commit: git2::Oid, decisions: history::Decisions) {
+        if let Some((c, d)) = &mut *self.explain.borrow_mut() {
+            if *c == commit {
+                // Filters are built from the inside out, so the last one is the outermost
+                *d = Some(decisions);
+            }
+        }
+    }
+
+    pub fn get(&self, filter: filter::Filter, from: git2::Oid) -> JoshResult<Option<git2::Oid>> {
+        if self.is_explaining(from) {
+            // Filter the commit again, so the decisions get recorded
+            return Ok(None);
+        }
+        if let Some(x) = self.get2(filter, from)? {
+            return Ok(Some(x));
         } else {
-            let mut t2 = self.t2.borrow_mut();
-            t2.misses += 1;
-            t2.missing.push((filter, from));
-            return None;
+            let mut walk = self.walk.borrow_mut();
+            walk.misses += 1;
+            walk.missing.push((filter, from));
+            return Ok(None);
         }
     }
 
-    fn get2(&self, filter: filter::Filter, from: git2::Oid) -> Option<git2::Oid> {
+    fn get2(&self, filter: filter::Filter, from: git2::Oid) -> JoshResult<Option<git2::Oid>> {
         if filter == filter::nop() {
-            return Some(from);
+            return Ok(Some(from));
         }
-        let mut t2 = self.t2.borrow_mut();
-        if let Some(m) = t2.commit_map.get(&filter.id()) {
-            if let Some(oid) = m.get(&from).cloned() {
-                return Some(oid);
+        let t = {
+            let mut t2 = self.t2.lock()?;
+            if let Some(m) = t2.commit_map.get(&filter.id()) {
+                if let Some(oid) = m.get(&from).cloned() {
+                    return Ok(Some(oid));
+                }
             }
-        }
-        let t = t2.sled_trees.entry(filter.id()).or_insert_with(|| {
-            DB.lock()
-                .unwrap()
-                .as_ref()
-                .unwrap()
-                .open_tree(filter::spec(filter))
-                .unwrap()
-        });
-        if let Some(oid) = t.get(from.as_bytes()).unwrap() {
-            let oid = git2::Oid::from_bytes(&oid).unwrap();
+            t2.filter_tree(filter, self.track_access.get())?
+        };
+        if let Some(oid) = t.get(from.as_bytes())? {
+            let oid = git2::Oid::from_bytes(&oid)?;
             if oid == git2::Oid::zero() {
-                return Some(oid);
+                return Ok(Some(oid));
             }
-            if self.repo.odb().unwrap().exists(oid) {
+            if self.repo.odb()?.exists(oid) {
                 // Only report an object as cached if it exists in the object database.
                 // This forces a rebuild in case the object was garbage collected.
-                return Some(oid);
+                return Ok(Some(oid));
             }
         }
 
-        return None;
+        return Ok(None);
+    }
+}
+
+fn open_repo(path: &std::path::Path) -> JoshResult<git2::Repository> {
+    Ok(git2::Repository::open_ext(
+        path,
+        git2::RepositoryOpenFlags::NO_SEARCH,
+        &[] as &[&std::ffi::OsStr],
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_db() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    fn add_marker(db: &sled::Db) -> JoshResult<()> {
+        let tree = db.open_tree("_meta")?;
+        let n = tree.get("marker")?.map(|v| v[0]).unwrap_or(0);
+        tree.insert("marker", vec![n + 1])?;
+        Ok(())
+    }
+
+    fn fail(_db: &sled::Db) -> JoshResult<()> {
+        Err(josh_error("migration failed"))
+    }
+
+    #[test]
+    fn lru_test() {
+        let mut lru = Lru::new(2);
+        lru.insert(1, "a");
+        lru.insert(2, "b");
+        assert_eq!(Some("a"), lru.get(&1));
+
+        // 2 is the least recently used entry now
+        lru.insert(3, "c");
+        assert_eq!(None, lru.get(&2));
+        assert_eq!(Some("a"), lru.get(&1));
+        assert_eq!(Some("c"), lru.get(&3));
+
+        // Replacing an entry does not evict another one
+        lru.insert(3, "d");
+        assert_eq!(Some("a"), lru.get(&1));
+        assert_eq!(Some("d"), lru.get(&3));
+    }
+
+    #[test]
+    fn migrate_test() {
+        let db = temporary_db();
+        write_version(&db, 3).unwrap();
+        let migrations: &[Migration] = &[(4, add_marker), (3, add_marker)];
+
+        assert!(migrate(&db, 3, 5, migrations).unwrap());
+        assert_eq!(Some(5), read_version(&db).unwrap());
+        assert_eq!(
+            Some(vec![2u8]),
+            db.open_tree("_meta")
+                .unwrap()
+                .get("marker")
+                .unwrap()
+                .map(|v| v.to_vec())
+        );
+
+        // Nothing to do when already up to date
+        assert!(migrate(&db, 5, 5, migrations).unwrap());
+    }
+
+    #[test]
+    fn migrate_missing_step_test() {
+        let db = temporary_db();
+        let migrations: &[Migration] = &[(3, add_marker)];
+
+        assert!(!migrate(&db, 2, 4, migrations).unwrap());
+        assert!(!migrate(&db, 3, 5, migrations).unwrap());
+        assert_eq!(Some(4), read_version(&db).unwrap());
+        assert!(migrate(&db, 1, 2, &[(1, fail)]).is_err());
+    }
+
+    #[test]
+    fn reserve_worker_test() {
+        set_jobs(3);
+        assert!(reserve_worker());
+        assert!(reserve_worker());
+        // The calling thread counts as one of the jobs
+        assert!(!reserve_worker());
+        release_worker();
+        assert!(reserve_worker());
+        release_worker();
+        release_worker();
+        set_jobs(1);
+        assert!(!reserve_worker());
+    }
+
+    #[test]
+    fn cancellation_test() {
+        let dir = std::env::temp_dir().join(format!("josh-cancellation-{}", std::process::id()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        load(repo.path()).unwrap();
+
+        let commit = {
+            let blob = repo.blob(b"contents").unwrap();
+            let mut sub = repo.treebuilder(None).unwrap();
+            sub.insert("file", blob, 0o100644).unwrap();
+            let mut root = repo.treebuilder(None).unwrap();
+            root.insert("sub", sub.write().unwrap(), 0o040000).unwrap();
+            let tree = repo.find_tree(root.write().unwrap()).unwrap();
+            let sig = git2::Signature::now("Josh", "josh@example.com").unwrap();
+            repo.commit(None, &sig, &sig, "initial", &tree, &[])
+                .unwrap()
+        };
+        let filter = filter::parse(":/sub").unwrap();
+
+        let transaction = Transaction::new(repo, None);
+        let cancellation = Cancellation::new();
+        transaction.set_cancellation(cancellation.clone());
+        assert!(transaction.check_cancelled().is_ok());
+
+        cancellation.cancel();
+        let c = transaction.repo().find_commit(commit).unwrap();
+        assert!(filter::apply_to_commit(filter, &c, &transaction).is_err());
+        assert!(!transaction.known(filter, commit).unwrap());
+        assert!(parallel(&transaction, vec![1, 2, 3], |_, x| Ok(x))
+            .unwrap()
+            .is_empty());
+
+        // Transactions with their own cancellation token are not affected
+        let transaction = Transaction::open(&dir, None).unwrap();
+        let c = transaction.repo().find_commit(commit).unwrap();
+        assert!(filter::apply_to_commit(filter, &c, &transaction).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn drop_filter_test() {
+        let dir = std::env::temp_dir().join(format!("josh-drop-filter-{}", std::process::id()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        load(repo.path()).unwrap();
+
+        let commit = |contents: &[u8], parents: &[git2::Oid]| {
+            let blob = repo.blob(contents).unwrap();
+            let mut sub = repo.treebuilder(None).unwrap();
+            sub.insert("file", blob, 0o100644).unwrap();
+            let mut root = repo.treebuilder(None).unwrap();
+            root.insert("sub", sub.write().unwrap(), 0o040000).unwrap();
+            let tree = repo.find_tree(root.write().unwrap()).unwrap();
+            let sig = git2::Signature::now("Josh", "josh@example.com").unwrap();
+            let parents = parents
+                .iter()
+                .map(|x| repo.find_commit(*x).unwrap())
+                .collect::<Vec<_>>();
+            repo.commit(
+                None,
+                &sig,
+                &sig,
+                "commit",
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )
+            .unwrap()
+        };
+        let first = commit(b"first", &[]);
+        let second = commit(b"second", &[first]);
+        let filter = filter::parse(":/sub").unwrap();
+
+        // The transaction keeps using the cache of the filter after it was dropped
+        let transaction = Transaction::open(&dir, None).unwrap();
+        let c = transaction.repo().find_commit(first).unwrap();
+        filter::apply_to_commit(filter, &c, &transaction).unwrap();
+        assert!(drop_filter(filter).unwrap());
+        let c = transaction.repo().find_commit(second).unwrap();
+        assert!(filter::apply_to_commit(filter, &c, &transaction).is_ok());
+        assert!(transaction.len(filter).unwrap() > 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn remove_legacy_dirs_test() {
+        let josh_dir =
+            std::env::temp_dir().join(format!("josh-legacy-dirs-{}", std::process::id()));
+        for version in &[VERSION - 1, VERSION + 1] {
+            std::fs::create_dir_all(josh_dir.join(format!("{}", version)).join("sled")).unwrap();
+        }
+
+        remove_legacy_dirs(&josh_dir).unwrap();
+        assert!(!josh_dir.join(format!("{}", VERSION - 1)).exists());
+        assert!(josh_dir.join(format!("{}", VERSION + 1)).exists());
+
+        std::fs::remove_dir_all(&josh_dir).unwrap();
     }
 }
@@ -60,6 +60,7 @@ enum Op {
     Workspace(std::path::PathBuf),
 
     Glob(String),
+    Since(git2::Oid),
 
     Compose(Vec<Filter>),
     Chain(Filter, Filter),
@@ -168,6 +169,7 @@ fn spec2(op: &Op) -> String {
         Op::File(path) => format!("::{}", path.to_string_lossy()),
         Op::Prefix(path) => format!(":prefix={}", path.to_string_lossy()),
         Op::Glob(pattern) => format!("::{}", pattern),
+        Op::Since(id) => format!(":since={}", id),
     }
 }
 
@@ -207,15 +209,14 @@ pub fn apply_to_commit(
     transaction: &cache::Transaction,
 ) -> JoshResult<git2::Oid> {
     for _ in 0..10000 {
+        transaction.check_cancelled()?;
         let filtered = apply_to_commit2(&to_op(filter), commit, transaction)?;
 
         if let Some(id) = filtered {
             return Ok(id);
         }
 
-        for (f, i) in transaction.get_missing() {
-            history::walk2(f, i, transaction)?;
-        }
+        history::walk_missing(transaction.get_missing()?, transaction)?;
     }
 
     Err(josh_error("apply_to_commit did not finish"))
@@ -229,6 +230,25 @@ pub fn apply_to_commit3(
     Ok(apply_to_commit2(&to_op(filter), commit, transaction)?.is_some())
 }
 
+/// Look up commits in the reverse index that might have been filtered to `filtered`.
+/// The result is not verified and can be incomplete, so callers need to check the
+/// candidates and fall back to searching the history.
+pub fn originals(
+    filter: Filter,
+    filtered: git2::Oid,
+    transaction: &cache::Transaction,
+) -> Vec<git2::Oid> {
+    let filter = opt::optimize(filter);
+    match to_op(filter) {
+        Op::Nop => vec![filtered],
+        Op::Chain(a, b) => originals(b, filtered, transaction)
+            .into_iter()
+            .flat_map(|intermediate| originals(a, intermediate, transaction))
+            .collect(),
+        _ => transaction.get_originals(filter, filtered),
+    }
+}
+
 fn apply_to_commit2(
     op: &Op,
     commit: &git2::Commit,
@@ -242,7 +262,7 @@ fn apply_to_commit2(
         Op::Empty => return Ok(Some(git2::Oid::zero())),
 
         Op::Chain(a, b) => {
-            let r = some_or!(apply_to_commit2(&to_op(*a), &commit, transaction)?, {
+            let r = some_or!(apply_to_commit2(&to_op(*a), commit, transaction)?, {
                 return Ok(None);
             });
             if let Ok(r) = repo.find_commit(r) {
@@ -251,17 +271,36 @@ fn apply_to_commit2(
                 return Ok(Some(git2::Oid::zero()));
             }
         }
+        Op::Since(since) => {
+            if commit.id() == *since {
+                return Some(history::create_filtered_commit(
+                    commit,
+                    vec![],
+                    commit.tree()?,
+                    transaction,
+                    filter,
+                ))
+                .transpose();
+            }
+            if let Some(oid) = transaction.get(filter, commit.id())? {
+                return Ok(Some(oid));
+            }
+            if !repo.graph_descendant_of(commit.id(), *since)? {
+                transaction.insert(filter, commit.id(), git2::Oid::zero(), true)?;
+                return Ok(Some(git2::Oid::zero()));
+            }
+        }
         Op::Squash => {
-            return Some(history::rewrite_commit(
-                &repo,
-                &commit,
-                &vec![],
+            return Some(history::rewrite_filtered_commit(
+                repo,
+                commit,
+                &[],
                 &commit.tree()?,
             ))
             .transpose()
         }
         _ => {
-            if let Some(oid) = transaction.get(filter, commit.id()) {
+            if let Some(oid) = transaction.get(filter, commit.id())? {
                 return Ok(Some(oid));
             }
         }
@@ -273,7 +312,7 @@ fn apply_to_commit2(
         Op::Compose(filters) => {
             let filtered = filters
                 .iter()
-                .map(|f| apply_to_commit2(&to_op(*f), &commit, transaction))
+                .map(|f| apply_to_commit2(&to_op(*f), commit, transaction))
                 .collect::<JoshResult<Option<Vec<_>>>>()?;
 
             let filtered = some_or!(filtered, { return Ok(None) });
@@ -282,22 +321,21 @@ fn apply_to_commit2(
                 .iter()
                 .zip(filtered.into_iter())
                 .filter(|(_, id)| *id != git2::Oid::zero())
-                .into_iter()
                 .map(|(f, id)| Ok((f, repo.find_commit(id)?.tree()?)))
                 .collect::<JoshResult<Vec<_>>>()?;
 
-            tree::compose(&transaction, filtered)?
+            tree::compose(transaction, filtered)?
         }
         Op::Workspace(ws_path) => {
             let normal_parents = commit
                 .parent_ids()
                 .map(|parent| transaction.get(filter, parent))
-                .collect::<Option<Vec<git2::Oid>>>();
+                .collect::<JoshResult<Option<Vec<git2::Oid>>>>()?;
 
             let normal_parents = some_or!(normal_parents, { return Ok(None) });
 
             let cw = parse::parse(&tree::get_blob(
-                &repo,
+                repo,
                 &commit.tree()?,
                 &ws_path.join("workspace.josh"),
             ))
@@ -308,8 +346,8 @@ fn apply_to_commit2(
                 .map(|parent| {
                     rs_tracing::trace_scoped!("parent", "id": parent.id().to_string());
                     let pcw = parse::parse(&tree::get_blob(
-                        &repo,
-                        &parent.tree().unwrap_or(tree::empty(&repo)),
+                        repo,
+                        &parent.tree().unwrap_or(tree::empty(repo)),
                         &ws_path.join("workspace.josh"),
                     ))
                     .unwrap_or(to_filter(Op::Empty));
@@ -320,10 +358,7 @@ fn apply_to_commit2(
 
             let extra_parents = some_or!(extra_parents, { return Ok(None) });
 
-            let filtered_parent_ids = normal_parents
-                .into_iter()
-                .chain(extra_parents.into_iter())
-                .collect();
+            let filtered_parent_ids = normal_parents.into_iter().chain(extra_parents).collect();
 
             let filtered_tree = apply(transaction, filter, commit.tree()?)?;
 
@@ -340,7 +375,7 @@ fn apply_to_commit2(
             let filtered_parent_ids = commit
                 .parents()
                 .map(|x| transaction.get(filter, x.id()))
-                .collect::<Option<Vec<_>>>();
+                .collect::<JoshResult<Option<Vec<_>>>>()?;
 
             let filtered_parent_ids = some_or!(filtered_parent_ids, { return Ok(None) });
 
@@ -352,7 +387,7 @@ fn apply_to_commit2(
             let mut filtered_tree = commit.tree_id();
 
             for t in trees {
-                filtered_tree = tree::overlay(&repo, filtered_tree, t)?;
+                filtered_tree = tree::overlay(repo, filtered_tree, t)?;
             }
 
             repo.find_tree(filtered_tree)?
@@ -362,7 +397,7 @@ fn apply_to_commit2(
                 transaction
                     .repo()
                     .find_commit(some_or!(
-                        apply_to_commit2(&to_op(*a), &commit, transaction)?,
+                        apply_to_commit2(&to_op(*a), commit, transaction)?,
                         { return Ok(None) }
                     ))
                     .map(|x| x.tree_id())
@@ -372,18 +407,19 @@ fn apply_to_commit2(
                 transaction
                     .repo()
                     .find_commit(some_or!(
-                        apply_to_commit2(&to_op(*b), &commit, transaction)?,
+                        apply_to_commit2(&to_op(*b), commit, transaction)?,
                         { return Ok(None) }
                     ))
                     .map(|x| x.tree_id())
                     .unwrap_or(tree::empty_id())
             };
             let bf = repo.find_tree(bf)?;
-            let bu = unapply(&transaction, *b, bf, tree::empty(&repo))?;
+            let bu = unapply(transaction, *b, bf, tree::empty(repo))?;
             let ba = apply(transaction, *a, bu)?;
 
-            repo.find_tree(tree::subtract(&repo, af, ba.id())?)?
+            repo.find_tree(tree::subtract(repo, af, ba.id())?)?
         }
+        Op::Since(_) => commit.tree()?,
         _ => apply(transaction, filter, commit.tree()?)?,
     };
 
@@ -392,7 +428,7 @@ fn apply_to_commit2(
         commit
             .parents()
             .map(|x| transaction.get(filter, x.id()))
-            .collect::<Option<_>>()
+            .collect::<JoshResult<Option<_>>>()?
     };
 
     let filtered_parent_ids = some_or!(filtered_parent_ids, { return Ok(None) });
@@ -424,9 +460,10 @@ fn apply2<'a>(
     let repo = transaction.repo();
     match op {
         Op::Nop => return Ok(tree),
-        Op::Empty => return Ok(tree::empty(&repo)),
+        Op::Empty => return Ok(tree::empty(repo)),
         Op::Fold => return Ok(tree),
         Op::Squash => return Ok(tree),
+        Op::Since(_) => return Ok(tree),
 
         Op::Glob(pattern) => {
             let pattern = glob::Pattern::new(pattern)?;
@@ -439,44 +476,43 @@ fn apply2<'a>(
                 transaction,
                 "",
                 tree.id(),
-                &|path, isblob| isblob && (pattern.matches_path_with(&path, options)),
+                &|path, isblob| isblob && (pattern.matches_path_with(path, options)),
                 to_filter(op.clone()).id(),
             )
         }
         Op::File(path) => {
             let (file, mode) = tree
-                .get_path(&path)
+                .get_path(path)
                 .map(|x| (x.id(), x.filemode()))
                 .unwrap_or((git2::Oid::zero(), 0o0100644));
-            if let Ok(_) = repo.find_blob(file) {
-                tree::insert(&repo, &tree::empty(&repo), &path, file, mode)
+            if repo.find_blob(file).is_ok() {
+                tree::insert(repo, &tree::empty(repo), path, file, mode)
             } else {
-                Ok(tree::empty(&repo))
+                Ok(tree::empty(repo))
             }
         }
 
         Op::Subdir(path) => {
             return Ok(tree
-                .get_path(&path)
+                .get_path(path)
                 .and_then(|x| repo.find_tree(x.id()))
-                .unwrap_or(tree::empty(&repo)));
+                .unwrap_or(tree::empty(repo)));
         }
-        Op::Prefix(path) => tree::insert(&repo, &tree::empty(&repo), &path, tree.id(), 0o0040000),
+        Op::Prefix(path) => tree::insert(repo, &tree::empty(repo), path, tree.id(), 0o0040000),
 
         Op::Subtract(a, b) => {
             let af = apply(transaction, *a, tree.clone())?;
             let bf = apply(transaction, *b, tree.clone())?;
-            let bu = unapply(transaction, *b, bf, tree::empty(&repo))?;
+            let bu = unapply(transaction, *b, bf, tree::empty(repo))?;
             let ba = apply(transaction, *a, bu)?;
-            Ok(repo.find_tree(tree::subtract(&repo, af.id(), ba.id())?)?)
+            Ok(repo.find_tree(tree::subtract(repo, af.id(), ba.id())?)?)
         }
 
         Op::Paths => tree::pathstree("", tree.id(), transaction),
 
         Op::Workspace(path) => {
             let base = to_filter(Op::Subdir(path.to_owned()));
-            if let Ok(cw) =
-                parse::parse(&tree::get_blob(&repo, &tree, &path.join("workspace.josh")))
+            if let Ok(cw) = parse::parse(&tree::get_blob(repo, &tree, &path.join("workspace.josh")))
             {
                 apply(transaction, compose(base, cw), tree)
             } else {
@@ -487,9 +523,9 @@ fn apply2<'a>(
         Op::Compose(filters) => {
             let filtered: Vec<_> = filters
                 .iter()
-                .map(|f| Ok(apply(transaction, *f, tree.clone())?))
+                .map(|f| apply(transaction, *f, tree.clone()))
                 .collect::<JoshResult<_>>()?;
-            let filtered: Vec<_> = filters.iter().zip(filtered.into_iter()).collect();
+            let filtered: Vec<_> = filters.iter().zip(filtered).collect();
             return tree::compose(transaction, filtered);
         }
 
@@ -519,6 +555,7 @@ fn unapply2<'a>(
     return match op {
         Op::Nop => Ok(tree),
         Op::Empty => Ok(parent_tree),
+        Op::Since(_) => Ok(tree),
 
         Op::Chain(a, b) => {
             let p = apply(transaction, *a, parent_tree.clone())?;
@@ -527,13 +564,13 @@ fn unapply2<'a>(
         }
         Op::Workspace(path) => {
             let root = to_filter(Op::Subdir(path.to_owned()));
-            let mapped = &tree::get_blob(&transaction.repo(), &tree, &Path::new("workspace.josh"));
+            let mapped = &tree::get_blob(transaction.repo(), &tree, Path::new("workspace.josh"));
             let parsed = parse(mapped)?;
 
             let mut blob = String::new();
             if let Ok(c) = get_comments(mapped) {
-                if c.len() > 0 {
-                    blob = format!("{}", c);
+                if !c.is_empty() {
+                    blob = c.to_string();
                 }
             }
             let blob = &format!("{}{}\n", &blob, pretty(parsed, 0));
@@ -541,9 +578,9 @@ fn unapply2<'a>(
             // Remove workspace.josh from the tree to prevent it from being parsed again
             // further down the callstack leading to endless recursion.
             let tree = tree::insert(
-                &transaction.repo(),
+                transaction.repo(),
                 &tree,
-                &Path::new("workspace.josh"),
+                Path::new("workspace.josh"),
                 git2::Oid::zero(),
                 0o0100644,
             )?;
@@ -551,9 +588,9 @@ fn unapply2<'a>(
             // Insert a dummy file to prevent the directory from dissappearing through becoming
             // empty.
             let tree = tree::insert(
-                &transaction.repo(),
+                transaction.repo(),
                 &tree,
-                &Path::new("DUMMY-df97a89d-b11f-4e1c-8400-345f895f0d40"),
+                Path::new("DUMMY-df97a89d-b11f-4e1c-8400-345f895f0d40"),
                 transaction.repo().blob("".as_bytes())?,
                 0o0100644,
             )?;
@@ -567,7 +604,7 @@ fn unapply2<'a>(
 
             // Remove the dummy file inserted above
             let r = tree::insert(
-                &transaction.repo(),
+                transaction.repo(),
                 &r,
                 &path.join("DUMMY-df97a89d-b11f-4e1c-8400-345f895f0d40"),
                 git2::Oid::zero(),
@@ -575,9 +612,9 @@ fn unapply2<'a>(
             )?;
 
             // Put the workspace.josh file back to it's target location.
-            let r = if mapped != "" {
+            let r = if !mapped.is_empty() {
                 tree::insert(
-                    &transaction.repo(),
+                    transaction.repo(),
                     &r,
                     &path.join("workspace.josh"),
                     transaction.repo().blob(blob.as_bytes())?,
@@ -598,7 +635,7 @@ fn unapply2<'a>(
                     transaction,
                     *other,
                     remaining.clone(),
-                    tree::empty(&transaction.repo()),
+                    tree::empty(transaction.repo()),
                 )?;
                 if tree::empty_id() == from_empty.id() {
                     continue;
@@ -607,7 +644,7 @@ fn unapply2<'a>(
                 let reapply = apply(transaction, *other, from_empty.clone())?;
 
                 remaining = transaction.repo().find_tree(tree::subtract(
-                    &transaction.repo(),
+                    transaction.repo(),
                     remaining.id(),
                     reapply.id(),
                 )?)?;
@@ -618,25 +655,25 @@ fn unapply2<'a>(
 
         Op::File(path) => {
             let (file, mode) = tree
-                .get_path(&path)
+                .get_path(path)
                 .map(|x| (x.id(), x.filemode()))
                 .unwrap_or((git2::Oid::zero(), 0o0100644));
-            if let Ok(_) = transaction.repo().find_blob(file) {
-                tree::insert(&transaction.repo(), &parent_tree, &path, file, mode)
+            if transaction.repo().find_blob(file).is_ok() {
+                tree::insert(transaction.repo(), &parent_tree, path, file, mode)
             } else {
-                Ok(tree::empty(&transaction.repo()))
+                Ok(tree::empty(transaction.repo()))
             }
         }
 
         Op::Subtract(a, b) => match (to_op(*a), to_op(*b)) {
             (Op::Nop, b) => {
                 let subtracted = tree::subtract(
-                    &transaction.repo(),
+                    transaction.repo(),
                     tree.id(),
-                    unapply2(transaction, &b, tree, tree::empty(&transaction.repo()))?.id(),
+                    unapply2(transaction, &b, tree, tree::empty(transaction.repo()))?.id(),
                 )?;
                 Ok(transaction.repo().find_tree(tree::overlay(
-                    &transaction.repo(),
+                    transaction.repo(),
                     parent_tree.id(),
                     subtracted,
                 )?)?)
@@ -654,30 +691,41 @@ fn unapply2<'a>(
                 transaction,
                 "",
                 tree.id(),
-                &|path, isblob| isblob && (pattern.matches_path_with(&path, options)),
+                &|path, isblob| isblob && (pattern.matches_path_with(path, options)),
                 to_filter(op.clone()).id(),
             )?;
             Ok(transaction.repo().find_tree(tree::overlay(
-                &transaction.repo(),
+                transaction.repo(),
                 parent_tree.id(),
                 subtracted.id(),
             )?)?)
         }
         Op::Prefix(path) => Ok(tree
-            .get_path(&path)
+            .get_path(path)
             .and_then(|x| transaction.repo().find_tree(x.id()))
-            .unwrap_or(tree::empty(&transaction.repo()))),
-        Op::Subdir(path) => tree::insert(
-            &transaction.repo(),
-            &parent_tree,
-            &path,
-            tree.id(),
-            0o0040000,
-        ),
+            .unwrap_or(tree::empty(transaction.repo()))),
+        Op::Subdir(path) => {
+            tree::insert(transaction.repo(), &parent_tree, path, tree.id(), 0o0040000)
+        }
         _ => return Err(josh_error("filter not reversible")),
     };
 }
 
+/// Create a filter that cuts off history below `id`: `id` itself becomes a root commit
+/// and commits that don't descend from it are dropped.
+pub fn since(id: git2::Oid) -> Filter {
+    to_filter(Op::Since(id))
+}
+
+/// The commit at which `filter` cuts off history, if it does.
+/// History walks for such filters don't need to go below this commit.
+pub fn cutoff(filter: Filter) -> Option<git2::Oid> {
+    if let Op::Since(id) = to_op(opt::optimize(filter)) {
+        return Some(id);
+    }
+    return None;
+}
+
 /// Create a filter that is the result of feeding the output of `first` into `second`
 pub fn chain(first: Filter, second: Filter) -> Filter {
     opt::optimize(to_filter(Op::Chain(first, second)))
@@ -688,6 +736,14 @@ pub fn compose(first: Filter, second: Filter) -> Filter {
     opt::optimize(to_filter(Op::Compose(vec![first, second])))
 }
 
+/// The members of a composed filter, or just the filter itself if it is not a composition
+pub fn members(filter: Filter) -> Vec<Filter> {
+    if let Op::Compose(filters) = to_op(opt::flatten(filter)) {
+        return filters;
+    }
+    return vec![filter];
+}
+
 /// Compute the warnings (filters not matching anything) for the filter applied to the tree
 pub fn compute_warnings<'a>(
     transaction: &'a cache::Transaction,
@@ -699,9 +755,9 @@ pub fn compute_warnings<'a>(
 
     if let Op::Workspace(path) = to_op(filter) {
         let workspace_filter = &tree::get_blob(
-            &transaction.repo(),
+            transaction.repo(),
             &tree,
-            &path.join(&Path::new("workspace.josh")),
+            &path.join(Path::new("workspace.josh")),
         );
         if let Ok(res) = parse(workspace_filter) {
             filter = res;
@@ -732,7 +788,7 @@ fn compute_warnings2<'a>(
 ) -> Vec<String> {
     let mut warnings = Vec::new();
 
-    let tree = apply(&transaction, filter, tree);
+    let tree = apply(transaction, filter, tree);
     if let Ok(tree) = tree {
         if tree.is_empty() {
             warnings.push(format!("No match for \"{}\"", pretty(filter, 2)));
@@ -72,9 +72,7 @@ pub fn simplify(filter: Filter) -> Filter {
             },
             (a, b) => Op::Chain(simplify(to_filter(a)), simplify(to_filter(b))),
         },
-        Op::Subtract(a, b) => match (to_op(a), to_op(b)) {
-            (a, b) => Op::Subtract(simplify(to_filter(a)), simplify(to_filter(b))),
-        },
+        Op::Subtract(a, b) => Op::Subtract(simplify(a), simplify(b)),
         _ => to_op(filter),
     });
 
@@ -125,9 +123,7 @@ pub fn flatten(filter: Filter) -> Filter {
             }
             _ => Op::Chain(flatten(af), flatten(bf)),
         },
-        Op::Subtract(a, b) => match (to_op(a), to_op(b)) {
-            (a, b) => Op::Subtract(flatten(to_filter(a)), flatten(to_filter(b))),
-        },
+        Op::Subtract(a, b) => Op::Subtract(flatten(a), flatten(b)),
         _ => to_op(filter),
     });
 
@@ -142,7 +138,7 @@ pub fn flatten(filter: Filter) -> Filter {
 fn group(filters: &Vec<Filter>) -> Vec<Vec<Filter>> {
     let mut res: Vec<Vec<Filter>> = vec![];
     for f in filters {
-        if res.len() == 0 {
+        if res.is_empty() {
             res.push(vec![*f]);
             continue;
         }
@@ -156,7 +152,7 @@ fn group(filters: &Vec<Filter>) -> Vec<Vec<Filter>> {
                 }
             }
         }
-        res.push(vec![f.clone()]);
+        res.push(vec![*f]);
     }
     if res.len() != filters.len() {
         return res;
@@ -164,8 +160,8 @@ fn group(filters: &Vec<Filter>) -> Vec<Vec<Filter>> {
 
     let mut res: Vec<Vec<Filter>> = vec![];
     for f in filters {
-        if res.len() == 0 {
-            res.push(vec![f.clone()]);
+        if res.is_empty() {
+            res.push(vec![*f]);
             continue;
         }
 
@@ -192,8 +188,8 @@ fn last_chain(rest: Filter, filter: Filter) -> (Filter, Filter) {
     }
 }
 
-fn prefix_sort(filters: &Vec<Filter>) -> Vec<Filter> {
-    let mut sorted = filters.clone();
+fn prefix_sort(filters: &[Filter]) -> Vec<Filter> {
+    let mut sorted = filters.to_vec();
     sorted.sort_by(|a, b| {
         let (src_a, src_b) = (src_path(*a), src_path(*b));
         if src_a.starts_with(&src_b) || src_b.starts_with(&src_a) {
@@ -215,7 +211,7 @@ fn common_pre(filters: &Vec<Filter>) -> Option<(Filter, Vec<Filter>)> {
     for f in filters {
         if let Op::Chain(a, b) = to_op(*f) {
             rest.push(b);
-            if c == None {
+            if c.is_none() {
                 c = Some(a);
             }
             if c != Some(a) {
@@ -239,7 +235,7 @@ fn common_post(filters: &Vec<Filter>) -> Option<(Filter, Vec<Filter>)> {
         let (a, b) = last_chain(to_filter(Op::Nop), *f);
         {
             rest.push(a);
-            if c == None {
+            if c.is_none() {
                 c = Some(b);
             }
             if c != Some(b) {
@@ -316,7 +312,7 @@ fn step(filter: Filter) -> Filter {
                 Op::Prefix(path)
             }
         }
-        Op::Compose(filters) if filters.len() == 0 => Op::Empty,
+        Op::Compose(filters) if filters.is_empty() => Op::Empty,
         Op::Compose(filters) if filters.len() == 1 => to_op(filters[0]),
         Op::Compose(mut filters) => {
             filters.dedup();
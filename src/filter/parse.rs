@@ -6,6 +6,7 @@ fn make_op(args: &[&str]) -> JoshResult<Op> {
         ["empty"] => Ok(Op::Empty),
         ["prefix", arg] => Ok(Op::Prefix(Path::new(arg).to_owned())),
         ["workspace", arg] => Ok(Op::Workspace(Path::new(arg).to_owned())),
+        ["since", arg] => Ok(Op::Since(git2::Oid::from_str(arg)?)),
         ["SQUASH"] => Ok(Op::Squash),
         ["PATHS"] => Ok(Op::Paths),
         ["FOLD"] => Ok(Op::Fold),
@@ -83,7 +84,7 @@ fn parse_file_entry(
         }
         Rule::filter_spec => {
             let filter = pair.as_str();
-            filters.push(parse(&filter)?);
+            filters.push(parse(filter)?);
             Ok(())
         }
         Rule::EOI => Ok(()),
@@ -132,7 +133,7 @@ fn parse_workspace(filter_spec: &str) -> JoshResult<Vec<Filter>> {
                     _ => return Err(josh_error(&format!("invalid workspace file {:?}", pair))),
                 };
             }
-            return Err(josh_error(&format!("invalid workspace file")));
+            return Err(josh_error("invalid workspace file"));
         }
         Err(r) => {
             return Err(josh_error(&format!(
@@ -146,7 +147,7 @@ fn parse_workspace(filter_spec: &str) -> JoshResult<Vec<Filter>> {
 
 /// Create a `Filter` from a string representation
 pub fn parse(filter_spec: &str) -> JoshResult<Filter> {
-    if filter_spec == "" {
+    if filter_spec.is_empty() {
         return Ok(to_filter(Op::Empty));
     }
     let mut chain: Option<Op> = None;
@@ -174,7 +175,7 @@ pub fn get_comments(filter_spec: &str) -> JoshResult<String> {
     if let Ok(r) = Grammar::parse(Rule::workspace_file, filter_spec) {
         let mut r = r;
         let r = r.next().unwrap();
-        for pair in r.into_inner() {
+        if let Some(pair) = r.into_inner().next() {
             return match pair.as_rule() {
                 Rule::workspace_comments => Ok(pair.as_str().to_string()),
                 Rule::compose => Ok("".to_string()),
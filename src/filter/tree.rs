@@ -11,26 +11,25 @@ pub fn pathstree<'a>(
     }
 
     let tree = repo.find_tree(input)?;
-    let mut result = tree::empty(&repo);
+    let mut result = tree::empty(repo);
 
     for entry in tree.iter() {
         let name = entry.name().ok_or(super::josh_error("no name"))?;
         if entry.kind() == Some(git2::ObjectType::Blob) {
-            let file_contents;
             let path = normalize_path(&std::path::Path::new(root).join(name));
             let path_string = path.to_str().ok_or(super::josh_error("no name"))?;
-            if name == "workspace.josh" {
-                file_contents = format!(
+            let file_contents = if name == "workspace.josh" {
+                format!(
                     "#{}\n{}",
                     path_string,
-                    get_blob(repo, &tree, &std::path::Path::new(&name))
-                );
+                    get_blob(repo, &tree, std::path::Path::new(&name))
+                )
             } else {
-                file_contents = path_string.to_string();
-            }
+                path_string.to_string()
+            };
             result = replace_child(
-                &repo,
-                &std::path::Path::new(name),
+                repo,
+                std::path::Path::new(name),
                 repo.blob(file_contents.as_bytes())?,
                 0o0100644,
                 &result,
@@ -39,14 +38,14 @@ pub fn pathstree<'a>(
 
         if entry.kind() == Some(git2::ObjectType::Tree) {
             let s = pathstree(
-                &format!("{}{}{}", root, if root == "" { "" } else { "/" }, name),
+                &format!("{}{}{}", root, if root.is_empty() { "" } else { "/" }, name),
                 entry.id(),
                 transaction,
             )?
             .id();
 
             if s != tree::empty_id() {
-                result = replace_child(&repo, &std::path::Path::new(name), s, 0o0040000, &result)?;
+                result = replace_child(repo, std::path::Path::new(name), s, 0o0040000, &result)?;
             }
         }
     }
@@ -68,7 +67,7 @@ pub fn remove_pred<'a>(
     rs_tracing::trace_scoped!("remove_pred X", "root": root);
 
     let tree = repo.find_tree(input)?;
-    let mut result = tree::empty(&repo);
+    let mut result = tree::empty(repo);
 
     for entry in tree.iter() {
         let name = entry.name().ok_or(super::josh_error("INVALID_FILENAME"))?;
@@ -76,8 +75,8 @@ pub fn remove_pred<'a>(
 
         if entry.kind() == Some(git2::ObjectType::Blob) && pred(&path, true) {
             result = replace_child(
-                &repo,
-                &std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
+                repo,
+                std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
                 entry.id(),
                 entry.filemode(),
                 &result,
@@ -85,7 +84,7 @@ pub fn remove_pred<'a>(
         }
 
         if entry.kind() == Some(git2::ObjectType::Tree) {
-            let s = if (root != "") && pred(&path, false) {
+            let s = if (!root.is_empty()) && pred(&path, false) {
                 entry.id()
             } else {
                 remove_pred(
@@ -93,7 +92,7 @@ pub fn remove_pred<'a>(
                     &format!(
                         "{}{}{}",
                         root,
-                        if root == "" { "" } else { "/" },
+                        if root.is_empty() { "" } else { "/" },
                         entry.name().ok_or(super::josh_error("no name"))?
                     ),
                     entry.id(),
@@ -105,8 +104,8 @@ pub fn remove_pred<'a>(
 
             if s != tree::empty_id() {
                 result = replace_child(
-                    &repo,
-                    &std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
+                    repo,
+                    std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
                     s,
                     0o0040000,
                     &result,
@@ -141,8 +140,8 @@ pub fn subtract(
         for entry in tree2.iter() {
             if let Some(e) = tree1.get_name(entry.name().ok_or(super::josh_error("no name"))?) {
                 result_tree = replace_child(
-                    &repo,
-                    &std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
+                    repo,
+                    std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
                     subtract(repo, e.id(), entry.id())?,
                     e.filemode(),
                     &result_tree,
@@ -164,10 +163,8 @@ fn replace_child<'a>(
     full_tree: &git2::Tree,
 ) -> super::JoshResult<git2::Tree<'a>> {
     let full_tree_id = {
-        let mut builder = repo.treebuilder(Some(&full_tree))?;
-        if oid == git2::Oid::zero() {
-            builder.remove(child).ok();
-        } else if oid == tree::empty_id() {
+        let mut builder = repo.treebuilder(Some(full_tree))?;
+        if oid == git2::Oid::zero() || oid == tree::empty_id() {
             builder.remove(child).ok();
         } else {
             builder.insert(child, oid, mode).ok();
@@ -185,20 +182,20 @@ pub fn insert<'a>(
     mode: i32,
 ) -> super::JoshResult<git2::Tree<'a>> {
     if path.components().count() == 1 {
-        return replace_child(&repo, path, oid, mode, full_tree);
+        return replace_child(repo, path, oid, mode, full_tree);
     } else {
         let name = std::path::Path::new(path.file_name().ok_or(super::josh_error("file_name"))?);
         let path = path.parent().ok_or(super::josh_error("path.parent"))?;
 
         let st = if let Ok(st) = full_tree.get_path(path) {
-            repo.find_tree(st.id()).unwrap_or(tree::empty(&repo))
+            repo.find_tree(st.id()).unwrap_or(tree::empty(repo))
         } else {
-            tree::empty(&repo)
+            tree::empty(repo)
         };
 
-        let tree = replace_child(&repo, name, oid, mode, &st)?;
+        let tree = replace_child(repo, name, oid, mode, &st)?;
 
-        return insert(&repo, full_tree, path, tree.id(), 0o0040000);
+        return insert(repo, full_tree, path, tree.id(), 0o0040000);
     }
 }
 
@@ -224,16 +221,16 @@ pub fn overlay(
         for entry in tree2.iter() {
             if let Some(e) = tree1.get_name(entry.name().ok_or(super::josh_error("no name"))?) {
                 result_tree = replace_child(
-                    &repo,
-                    &std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
+                    repo,
+                    std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
                     overlay(repo, entry.id(), e.id())?,
                     e.filemode(),
                     &result_tree,
                 )?;
             } else {
                 result_tree = replace_child(
-                    &repo,
-                    &std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
+                    repo,
+                    std::path::Path::new(entry.name().ok_or(super::josh_error("no name"))?),
                     entry.id(),
                     entry.filemode(),
                     &result_tree,
@@ -248,12 +245,11 @@ pub fn overlay(
 }
 
 pub fn pathline(b: &str) -> JoshResult<String> {
-    for line in b.split("\n") {
-        let l = line.trim_start_matches("#");
-        if l == "" {
-            break;
+    if let Some(line) = b.split('\n').next() {
+        let l = line.trim_start_matches('#');
+        if !l.is_empty() {
+            return Ok(l.to_string());
         }
-        return Ok(l.to_string());
     }
     return Err(josh_error("pathline"));
 }
@@ -268,7 +264,7 @@ pub fn invert_paths<'a>(
         return Ok(repo.find_tree(cached)?);
     }
 
-    let mut result = tree::empty(&repo);
+    let mut result = tree::empty(repo);
 
     for entry in tree.iter() {
         let name = entry.name().ok_or(super::josh_error("no name"))?;
@@ -277,13 +273,13 @@ pub fn invert_paths<'a>(
             let mpath = normalize_path(&std::path::Path::new(root).join(name))
                 .to_string_lossy()
                 .to_string();
-            let b = tree::get_blob(&repo, &tree, &std::path::Path::new(name));
+            let b = tree::get_blob(repo, &tree, std::path::Path::new(name));
             let opath = pathline(&b)?;
 
             result = insert(
-                &repo,
+                repo,
                 &result,
-                &std::path::Path::new(&opath),
+                std::path::Path::new(&opath),
                 repo.blob(mpath.as_bytes())?,
                 0o0100644,
             )
@@ -292,11 +288,11 @@ pub fn invert_paths<'a>(
 
         if entry.kind() == Some(git2::ObjectType::Tree) {
             let s = invert_paths(
-                &transaction,
-                &format!("{}{}{}", root, if root == "" { "" } else { "/" }, name),
+                transaction,
+                &format!("{}{}{}", root, if root.is_empty() { "" } else { "/" }, name),
                 repo.find_tree(entry.id())?,
             )?;
-            result = repo.find_tree(overlay(&repo, result.id(), s.id())?)?;
+            result = repo.find_tree(overlay(repo, result.id(), s.id())?)?;
         }
     }
 
@@ -343,11 +339,11 @@ fn populate(
 
     let mut result_tree = empty_id();
     if let (Ok(paths), Ok(content)) = (repo.find_blob(paths), repo.find_blob(content)) {
-        let ipath = pathline(&std::str::from_utf8(paths.content())?)?;
+        let ipath = pathline(std::str::from_utf8(paths.content())?)?;
         result_tree = insert(
-            &repo,
+            repo,
             &repo.find_tree(result_tree)?,
-            &std::path::Path::new(&ipath),
+            std::path::Path::new(&ipath),
             content.id(),
             0o0100644,
         )?
@@ -356,7 +352,7 @@ fn populate(
         for entry in content.iter() {
             if let Some(e) = paths.get_name(entry.name().ok_or(super::josh_error("no name"))?) {
                 result_tree = overlay(
-                    &repo,
+                    repo,
                     result_tree,
                     populate(transaction, e.id(), entry.id())?,
                 )?;
@@ -375,8 +371,8 @@ pub fn compose<'a>(
 ) -> super::JoshResult<git2::Tree<'a>> {
     rs_tracing::trace_scoped!("compose");
     let repo = transaction.repo();
-    let mut result = tree::empty(&repo);
-    let mut taken = tree::empty(&repo);
+    let mut result = tree::empty(repo);
+    let mut taken = tree::empty(repo);
     for (f, applied) in trees {
         let tid = taken.id();
         let taken_applied = if let Some(cached) = transaction.get_apply(*f, tid) {
@@ -386,24 +382,97 @@ pub fn compose<'a>(
         };
         transaction.insert_apply(*f, tid, taken_applied);
 
-        let subtracted = repo.find_tree(subtract(&repo, applied.id(), taken_applied)?)?;
+        let subtracted = repo.find_tree(subtract(repo, applied.id(), taken_applied)?)?;
 
         let aid = applied.id();
         let unapplied = if let Some(cached) = transaction.get_unapply(*f, aid) {
             cached
         } else {
-            filter::unapply(transaction, *f, applied, empty(&repo))?.id()
+            filter::unapply(transaction, *f, applied, empty(repo))?.id()
         };
         transaction.insert_unapply(*f, aid, unapplied);
-        taken = repo.find_tree(overlay(&repo, taken.id(), unapplied)?)?;
-        result = repo.find_tree(overlay(&repo, result.id(), subtracted.id())?)?;
+        taken = repo.find_tree(overlay(repo, taken.id(), unapplied)?)?;
+        result = repo.find_tree(overlay(repo, result.id(), subtracted.id())?)?;
     }
 
     Ok(result)
 }
 
+/// Find all lines of text files in `tree` that contain `pattern`, or match it
+/// as a regular expression if `regex` is set. The search stops after `max_results`
+/// matching lines.
+pub fn search(
+    transaction: &cache::Transaction,
+    tree: &git2::Tree,
+    pattern: &str,
+    regex: bool,
+    max_results: Option<usize>,
+) -> JoshResult<cache::SearchResults> {
+    let key = (tree.id(), pattern.to_string(), regex);
+    if let Some((cached, complete)) = transaction.get_search(&key) {
+        if complete || max_results.map(|m| cached.len() >= m).unwrap_or(false) {
+            return Ok(cached);
+        }
+    }
+
+    let re = if regex {
+        Some(regex::Regex::new(pattern)?)
+    } else {
+        None
+    };
+    let repo = transaction.repo();
+    let max_results = max_results.unwrap_or(usize::MAX);
+
+    let mut results = vec![];
+    let mut err = None;
+    let walked = tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() != Some(git2::ObjectType::Blob) {
+            return git2::TreeWalkResult::Ok;
+        }
+        let blob = match repo.find_blob(entry.id()) {
+            Ok(blob) => blob,
+            Err(e) => {
+                err = Some(e);
+                return git2::TreeWalkResult::Abort;
+            }
+        };
+        if blob.is_binary() {
+            return git2::TreeWalkResult::Ok;
+        }
+        let path = format!("{}{}", root, entry.name().unwrap_or(""));
+        let content = String::from_utf8_lossy(blob.content());
+        for (n, line) in content.lines().enumerate() {
+            let m = if let Some(re) = &re {
+                re.is_match(line)
+            } else {
+                line.contains(pattern)
+            };
+            if m {
+                results.push((path.clone(), n + 1, line.to_string()));
+                if results.len() >= max_results {
+                    return git2::TreeWalkResult::Abort;
+                }
+            }
+        }
+        git2::TreeWalkResult::Ok
+    });
+    if let Some(e) = err {
+        return Err(e.into());
+    }
+
+    // Aborting the walk makes it return an error, which is expected when stopping early
+    let complete = results.len() < max_results;
+    if complete {
+        walked?;
+    }
+
+    let results = std::sync::Arc::new(results);
+    transaction.insert_search(key, results.clone(), complete);
+    return Ok(results);
+}
+
 pub fn get_blob(repo: &git2::Repository, tree: &git2::Tree, path: &Path) -> String {
-    let entry_oid = ok_or!(tree.get_path(&path).map(|x| x.id()), {
+    let entry_oid = ok_or!(tree.get_path(path).map(|x| x.id()), {
         return "".to_owned();
     });
 
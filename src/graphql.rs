@@ -16,7 +16,7 @@ fn find_paths(
     kind: git2::ObjectType,
 ) -> JoshResult<Vec<std::path::PathBuf>> {
     let tree = if let Some(at) = at.as_ref() {
-        if at == "" {
+        if at.is_empty() {
             tree
         } else {
             let path = std::path::Path::new(&at).to_owned();
@@ -54,7 +54,7 @@ impl Revision {
         context: &Context,
         kind: git2::ObjectType,
     ) -> FieldResult<Option<Vec<Path>>> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let commit = transaction.repo().find_commit(self.commit_id)?;
         let tree = filter::apply(&transaction, self.filter, commit.tree()?)?;
         let tree_id = tree.id();
@@ -70,6 +70,15 @@ impl Revision {
         }
         return Ok(Some(ws));
     }
+
+    fn filtered_commit<'a>(
+        &self,
+        transaction: &'a cache::Transaction,
+    ) -> JoshResult<git2::Commit<'a>> {
+        let commit = transaction.repo().find_commit(self.commit_id)?;
+        let filtered = filter::apply_to_commit(self.filter, &commit, transaction)?;
+        return Ok(transaction.repo().find_commit(filtered)?);
+    }
 }
 
 #[graphql_object(context = Context)]
@@ -79,14 +88,14 @@ impl Revision {
     }
 
     fn hash(&self, context: &Context) -> FieldResult<String> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let commit = transaction.repo().find_commit(self.commit_id)?;
         let filter_commit = filter::apply_to_commit(self.filter, &commit, &transaction)?;
         Ok(format!("{}", filter_commit))
     }
 
     fn summary(&self, context: &Context) -> FieldResult<String> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let commit = transaction.repo().find_commit(self.commit_id)?;
         let filter_commit = transaction.repo().find_commit(filter::apply_to_commit(
             self.filter,
@@ -97,7 +106,7 @@ impl Revision {
     }
 
     fn date(&self, format: String, context: &Context) -> FieldResult<String> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let commit = transaction.repo().find_commit(self.commit_id)?;
         let filter_commit = transaction.repo().find_commit(filter::apply_to_commit(
             self.filter,
@@ -111,6 +120,50 @@ impl Revision {
         Ok(ndt.format(&format).to_string())
     }
 
+    fn message(&self, context: &Context) -> FieldResult<String> {
+        let transaction = context.transaction()?;
+        let commit = self.filtered_commit(&transaction)?;
+        Ok(commit.message().unwrap_or("").to_owned())
+    }
+
+    fn author(&self, context: &Context) -> FieldResult<Signature> {
+        let transaction = context.transaction()?;
+        let commit = self.filtered_commit(&transaction)?;
+        Ok(Signature::from(commit.author()))
+    }
+
+    fn committer(&self, context: &Context) -> FieldResult<Signature> {
+        let transaction = context.transaction()?;
+        let commit = self.filtered_commit(&transaction)?;
+        Ok(Signature::from(commit.committer()))
+    }
+
+    fn trailers(&self, context: &Context) -> FieldResult<Vec<Trailer>> {
+        let transaction = context.transaction()?;
+        let commit = self.filtered_commit(&transaction)?;
+        Ok(parse_trailers(commit.message().unwrap_or("")))
+    }
+
+    fn change_id(&self, context: &Context) -> FieldResult<Option<String>> {
+        let transaction = context.transaction()?;
+        let commit = self.filtered_commit(&transaction)?;
+        Ok(get_change_id(&commit))
+    }
+
+    fn original(&self) -> String {
+        format!("{}", self.commit_id)
+    }
+
+    fn filtered(&self, context: &Context) -> FieldResult<String> {
+        let transaction = context.transaction()?;
+        Ok(format!("{}", self.filtered_commit(&transaction)?.id()))
+    }
+
+    fn tree(&self, context: &Context) -> FieldResult<String> {
+        let transaction = context.transaction()?;
+        Ok(format!("{}", self.filtered_commit(&transaction)?.tree_id()))
+    }
+
     fn rev(
         &self,
         filter: Option<String>,
@@ -118,7 +171,7 @@ impl Revision {
         context: &Context,
     ) -> FieldResult<Option<Revision>> {
         let commit_id = if let Some(true) = original {
-            let transaction = context.transaction.lock()?;
+            let transaction = context.transaction()?;
             let commit = transaction.repo().find_commit(self.commit_id)?;
             let filter_commit = transaction.repo().find_commit(filter::apply_to_commit(
                 self.filter,
@@ -143,7 +196,7 @@ impl Revision {
     }
 
     fn parents(&self, context: &Context) -> FieldResult<Vec<Revision>> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let commit = transaction.repo().find_commit(self.commit_id)?;
         let filter_commit = transaction.repo().find_commit(filter::apply_to_commit(
             self.filter,
@@ -163,6 +216,83 @@ impl Revision {
         Ok(parents)
     }
 
+    fn history(
+        &self,
+        first: Option<i32>,
+        after: Option<String>,
+        path: Option<String>,
+        context: &Context,
+    ) -> FieldResult<HistoryConnection> {
+        let transaction = context.transaction()?;
+        let commit = transaction.repo().find_commit(self.commit_id)?;
+        let filtered = filter::apply_to_commit(self.filter, &commit, &transaction)?;
+
+        let mut edges = vec![];
+        let mut has_next_page = false;
+
+        if filtered != git2::Oid::zero() {
+            let repo = transaction.repo();
+            let path = path.filter(|x| !x.is_empty()).map(std::path::PathBuf::from);
+
+            // The walk goes newest first, with the commits it has not reached yet kept
+            // as a frontier ordered by commit time. So every page only touches the
+            // commits on it (and the ones skipped for not changing `path`), and the
+            // frontier is all that is needed to continue, which makes it the cursor.
+            let start = match after {
+                Some(after) => parse_cursor(repo, filtered, &after)?,
+                None => vec![filtered],
+            };
+            let mut pending = std::collections::BTreeSet::new();
+            for id in start {
+                pending.insert((repo.find_commit(id)?.time().seconds(), id));
+            }
+
+            let mut visited = std::collections::HashSet::new();
+            let mut page = vec![];
+            while let Some((time, id)) = pending.iter().next_back().cloned() {
+                let include = match &path {
+                    Some(path) => changes_path(repo, id, path)?,
+                    None => true,
+                };
+                if include {
+                    if let Some(first) = first {
+                        if page.len() >= first.max(0) as usize {
+                            has_next_page = true;
+                            break;
+                        }
+                    }
+                }
+                pending.remove(&(time, id));
+                visited.insert(id);
+                for parent in repo.find_commit(id)?.parents() {
+                    if !visited.contains(&parent.id()) {
+                        pending.insert((parent.time().seconds(), parent.id()));
+                    }
+                }
+                if include {
+                    page.push((id, format_cursor(pending.iter().map(|(_, id)| id))));
+                }
+            }
+
+            let filtered_ids: Vec<_> = page.iter().map(|(id, _)| *id).collect();
+            let originals =
+                history::find_originals(&transaction, self.filter, self.commit_id, &filtered_ids)?;
+
+            for (id, cursor) in page {
+                edges.push(HistoryEdge {
+                    filter: self.filter,
+                    original: originals.get(&id).cloned(),
+                    cursor,
+                });
+            }
+        }
+
+        Ok(HistoryConnection {
+            edges,
+            has_next_page,
+        })
+    }
+
     fn files(
         &self,
         at: Option<String>,
@@ -182,7 +312,7 @@ impl Revision {
     }
 
     fn file(&self, path: String, context: &Context) -> FieldResult<Option<Path>> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let path = std::path::Path::new(&path).to_owned();
         let tree = transaction.repo().find_commit(self.commit_id)?.tree()?;
 
@@ -202,7 +332,7 @@ impl Revision {
 
     fn dir(&self, path: Option<String>, context: &Context) -> FieldResult<Option<Path>> {
         let path = path.unwrap_or_default();
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let tree = transaction.repo().find_commit(self.commit_id)?.tree()?;
 
         let tree = filter::apply(&transaction, self.filter, tree)?;
@@ -230,8 +360,86 @@ impl Revision {
         }
     }
 
+    fn diff(
+        &self,
+        against: String,
+        filter: Option<String>,
+        context: &Context,
+    ) -> FieldResult<Vec<DiffEntry>> {
+        let transaction = context.transaction()?;
+        let other_filter = if let Some(filter) = filter {
+            filter::parse(&filter)?
+        } else {
+            self.filter
+        };
+        let other = transaction
+            .repo()
+            .find_commit(resolve_commit(&transaction, &against)?)?;
+        let old_tree = filter::apply(&transaction, other_filter, other.tree()?)?;
+        let new_tree = filter::apply(
+            &transaction,
+            self.filter,
+            transaction.repo().find_commit(self.commit_id)?.tree()?,
+        )?;
+
+        Ok(diff_trees(transaction.repo(), &old_tree, &new_tree)?)
+    }
+
+    fn search(
+        &self,
+        pattern: String,
+        regex: Option<bool>,
+        max_results: Option<i32>,
+        context: &Context,
+    ) -> FieldResult<Vec<SearchResult>> {
+        let transaction = context.transaction()?;
+        let commit = transaction.repo().find_commit(self.commit_id)?;
+        let tree = filter::apply(&transaction, self.filter, commit.tree()?)?;
+        let max_results = max_results.map(|x| x.max(0) as usize);
+        let results = filter::tree::search(
+            &transaction,
+            &tree,
+            &pattern,
+            regex.unwrap_or(false),
+            max_results,
+        )?;
+        let max_results = max_results.unwrap_or(results.len());
+
+        Ok(results
+            .iter()
+            .take(max_results)
+            .map(|(path, line, text)| SearchResult {
+                path: path.clone(),
+                line: *line as i32,
+                text: text.clone(),
+            })
+            .collect())
+    }
+
+    fn explain(&self, context: &Context) -> FieldResult<Explanation> {
+        let transaction = context.transaction()?;
+        let commit = transaction.repo().find_commit(self.commit_id)?;
+        let e = history::explain(&transaction, self.filter, &commit)?;
+        Ok(Explanation {
+            filtered: if e.filtered == git2::Oid::zero() {
+                None
+            } else {
+                Some(format!("{}", e.filtered))
+            },
+            touched_by: e.touched_by.into_iter().map(filter::spec).collect(),
+            tree_unchanged: e.decisions.tree_unchanged,
+            dropped_parents: e
+                .decisions
+                .dropped_parents
+                .iter()
+                .map(|x| format!("{}", x))
+                .collect(),
+            collapsed_into: e.decisions.collapsed_into.map(|x| format!("{}", x)),
+        })
+    }
+
     fn warnings(&self, context: &Context) -> FieldResult<Option<Vec<Warning>>> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let commit = transaction.repo().find_commit(self.commit_id)?;
 
         let warnings = filter::compute_warnings(&transaction, self.filter, commit.tree()?)
@@ -241,6 +449,493 @@ impl Revision {
 
         Ok(Some(warnings))
     }
+
+    fn markers(
+        &self,
+        topic: String,
+        conditions: Option<Vec<MarkerCondition>>,
+        context: &Context,
+    ) -> FieldResult<Vec<Marker>> {
+        let transaction = context.transaction()?;
+        let repo = transaction.repo();
+        let (mtree, _) = meta_tree(&transaction)?;
+
+        let commit = self.commit_id.to_string();
+        let mtree = mtree
+            .get_path(&marker_path(&commit, &topic))
+            .map(|p| repo.find_tree(p.id()).ok())
+            .ok()
+            .flatten()
+            .unwrap_or(filter::tree::empty(repo));
+
+        let mtree = if self.filter == filter::nop() {
+            mtree
+        } else {
+            repo.find_tree(filter::tree::repopulated_tree(
+                &transaction,
+                self.filter,
+                repo.find_commit(self.commit_id)?.tree()?,
+                mtree,
+            )?)?
+        };
+
+        let mut blobs = vec![];
+        mtree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                blobs.push((
+                    format!("{}{}", root, entry.name().unwrap_or("")),
+                    entry.id(),
+                ));
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+
+        let conditions = conditions.unwrap_or_default();
+        let mut markers = vec![];
+        for (path, id) in blobs {
+            let blob = repo.find_blob(id)?;
+            for line in std::str::from_utf8(blob.content())?.split("\n") {
+                if line.is_empty() {
+                    continue;
+                }
+                let data = parse_marker(line);
+                if marker_matches(&data, &conditions) {
+                    markers.push(Marker {
+                        path: path.clone(),
+                        data,
+                    });
+                }
+            }
+        }
+
+        Ok(markers)
+    }
+}
+
+/// Whether `commit_id` changed what is at `path`, compared to any of its parents
+fn changes_path(
+    repo: &git2::Repository,
+    commit_id: git2::Oid,
+    path: &std::path::Path,
+) -> JoshResult<bool> {
+    let commit = repo.find_commit(commit_id)?;
+    let entry_id = |tree: git2::Tree| {
+        tree.get_path(path)
+            .map(|x| x.id())
+            .unwrap_or(git2::Oid::zero())
+    };
+    let id = entry_id(commit.tree()?);
+    if commit.parent_count() == 0 {
+        return Ok(id != git2::Oid::zero());
+    }
+    for parent in commit.parents() {
+        if entry_id(parent.tree()?) != id {
+            return Ok(true);
+        }
+    }
+    return Ok(false);
+}
+
+pub struct HistoryConnection {
+    edges: Vec<HistoryEdge>,
+    has_next_page: bool,
+}
+
+#[graphql_object(context = Context)]
+impl HistoryConnection {
+    fn edges(&self) -> Vec<HistoryEdge> {
+        self.edges.clone()
+    }
+
+    fn nodes(&self) -> Vec<Option<Revision>> {
+        self.edges
+            .iter()
+            .map(|x| {
+                x.original.map(|commit_id| Revision {
+                    filter: x.filter,
+                    commit_id,
+                })
+            })
+            .collect()
+    }
+
+    fn page_info(&self) -> PageInfo {
+        PageInfo {
+            has_next_page: self.has_next_page,
+            end_cursor: self.edges.last().map(|x| x.cursor.clone()),
+        }
+    }
+}
+
+/// `original` is `None` if no original could be found for the filtered commit.
+#[derive(Clone)]
+pub struct HistoryEdge {
+    filter: filter::Filter,
+    original: Option<git2::Oid>,
+    cursor: String,
+}
+
+#[graphql_object(context = Context)]
+impl HistoryEdge {
+    fn cursor(&self) -> String {
+        self.cursor.clone()
+    }
+
+    fn node(&self) -> Option<Revision> {
+        self.original.map(|commit_id| Revision {
+            filter: self.filter,
+            commit_id,
+        })
+    }
+}
+
+pub struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[graphql_object(context = Context)]
+impl PageInfo {
+    fn has_next_page(&self) -> bool {
+        self.has_next_page
+    }
+
+    fn end_cursor(&self) -> Option<String> {
+        self.end_cursor.clone()
+    }
+}
+
+fn format_cursor<'a>(pending: impl Iterator<Item = &'a git2::Oid>) -> String {
+    return pending
+        .map(|x| format!("{}", x))
+        .collect::<Vec<_>>()
+        .join(",");
+}
+
+/// Parse a cursor of the history of `filtered`. All commits in it have to be part of
+/// that history, cursors of other histories are rejected.
+fn parse_cursor(
+    repo: &git2::Repository,
+    filtered: git2::Oid,
+    cursor: &str,
+) -> JoshResult<Vec<git2::Oid>> {
+    let mut ids = vec![];
+    for id in cursor.split(',').filter(|x| !x.is_empty()) {
+        let id = git2::Oid::from_str(id)
+            .map_err(|_| josh_error(&format!("invalid cursor: {:?}", cursor)))?;
+        if id != filtered && !repo.graph_descendant_of(filtered, id)? {
+            return Err(josh_error(&format!("unknown cursor: {:?}", cursor)));
+        }
+        ids.push(id);
+    }
+    return Ok(ids);
+}
+
+/// Find the commit `rev` points to, either given as a commit id or a reference
+/// relative to the repository the transaction belongs to
+fn resolve_commit(transaction: &cache::Transaction, rev: &str) -> JoshResult<git2::Oid> {
+    if let Ok(id) = git2::Oid::from_str(rev) {
+        return Ok(id);
+    }
+    let object = transaction
+        .repo()
+        .revparse_single(&transaction.refname(rev))
+        .or_else(|_| transaction.repo().revparse_single(rev))?;
+    return Ok(object.peel_to_commit()?.id());
+}
+
+fn diff_trees(
+    repo: &git2::Repository,
+    old_tree: &git2::Tree,
+    new_tree: &git2::Tree,
+) -> JoshResult<Vec<DiffEntry>> {
+    let mut diff = repo.diff_tree_to_tree(Some(old_tree), Some(new_tree), None)?;
+    diff.find_similar(None)?;
+
+    let mut entries = vec![];
+    for delta in diff.deltas() {
+        let status = match delta.status() {
+            git2::Delta::Added => DiffStatus::Added,
+            git2::Delta::Deleted => DiffStatus::Deleted,
+            git2::Delta::Renamed => DiffStatus::Renamed,
+            _ => DiffStatus::Modified,
+        };
+        entries.push(DiffEntry {
+            status,
+            old_path: delta
+                .old_file()
+                .path()
+                .unwrap_or(std::path::Path::new(""))
+                .to_owned(),
+            new_path: delta
+                .new_file()
+                .path()
+                .unwrap_or(std::path::Path::new(""))
+                .to_owned(),
+            old_id: delta.old_file().id(),
+            new_id: delta.new_file().id(),
+        });
+    }
+    return Ok(entries);
+}
+
+/// Unified diff between two blobs, a zero id stands for a file that does not exist.
+/// There is no patch for binary files.
+fn blob_patch(
+    repo: &git2::Repository,
+    old: (git2::Oid, &std::path::Path),
+    new: (git2::Oid, &std::path::Path),
+    context_lines: Option<i32>,
+) -> JoshResult<Option<String>> {
+    let old_blob = repo.find_blob(old.0).ok();
+    let new_blob = repo.find_blob(new.0).ok();
+    if old_blob
+        .iter()
+        .chain(new_blob.iter())
+        .any(|x| x.is_binary())
+    {
+        return Ok(None);
+    }
+    let mut opts = git2::DiffOptions::new();
+    if let Some(n) = context_lines {
+        opts.context_lines(n.max(0) as u32);
+    }
+
+    let old_path = old.1.to_string_lossy();
+    let new_path = new.1.to_string_lossy();
+    let side = |blob: &Option<git2::Blob>, prefix: &str, path: &str| {
+        if blob.is_some() {
+            format!("{}{}", prefix, path)
+        } else {
+            "/dev/null".to_string()
+        }
+    };
+    let mut patch = format!(
+        "diff --git a/{} b/{}\n--- {}\n+++ {}\n",
+        old_path,
+        new_path,
+        side(&old_blob, "a/", &old_path),
+        side(&new_blob, "b/", &new_path)
+    );
+
+    // git2::Patch can't represent a missing side, so the patch is assembled from
+    // the lines of the diff
+    let mut hunk_header = None;
+    repo.diff_blobs(
+        old_blob.as_ref(),
+        Some(&old_path),
+        new_blob.as_ref(),
+        Some(&new_path),
+        Some(&mut opts),
+        None,
+        None,
+        None,
+        Some(&mut |_, hunk, line| {
+            if let Some(hunk) = hunk {
+                let header = hunk.header().to_vec();
+                if hunk_header.as_ref() != Some(&header) {
+                    patch.push_str(&String::from_utf8_lossy(&header));
+                    hunk_header = Some(header);
+                }
+            }
+            if let ' ' | '+' | '-' = line.origin() {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        }),
+    )?;
+    return Ok(Some(patch));
+}
+
+#[derive(juniper::GraphQLEnum, Clone, Copy, PartialEq, Debug)]
+pub enum DiffStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+}
+
+pub struct DiffEntry {
+    status: DiffStatus,
+    old_path: std::path::PathBuf,
+    new_path: std::path::PathBuf,
+    old_id: git2::Oid,
+    new_id: git2::Oid,
+}
+
+#[graphql_object(context = Context)]
+impl DiffEntry {
+    fn path(&self) -> String {
+        if self.status == DiffStatus::Deleted {
+            self.old_path.to_string_lossy().to_string()
+        } else {
+            self.new_path.to_string_lossy().to_string()
+        }
+    }
+
+    fn old_path(&self) -> Option<String> {
+        if self.status == DiffStatus::Renamed {
+            Some(self.old_path.to_string_lossy().to_string())
+        } else {
+            None
+        }
+    }
+
+    fn status(&self) -> DiffStatus {
+        self.status
+    }
+
+    fn patch(&self, context_lines: Option<i32>, context: &Context) -> FieldResult<Option<String>> {
+        let transaction = context.transaction()?;
+        Ok(blob_patch(
+            transaction.repo(),
+            (self.old_id, &self.old_path),
+            (self.new_id, &self.new_path),
+            context_lines,
+        )?)
+    }
+}
+
+pub struct SearchResult {
+    path: String,
+    line: i32,
+    text: String,
+}
+
+#[graphql_object(context = Context)]
+impl SearchResult {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn line(&self) -> i32 {
+        self.line
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// GraphQL view of `history::Explanation`
+pub struct Explanation {
+    filtered: Option<String>,
+    touched_by: Vec<String>,
+    tree_unchanged: bool,
+    dropped_parents: Vec<String>,
+    collapsed_into: Option<String>,
+}
+
+#[graphql_object(context = Context)]
+impl Explanation {
+    fn filtered(&self) -> Option<String> {
+        self.filtered.clone()
+    }
+
+    fn touched_by(&self) -> Vec<String> {
+        self.touched_by.clone()
+    }
+
+    fn tree_unchanged(&self) -> bool {
+        self.tree_unchanged
+    }
+
+    fn dropped_parents(&self) -> Vec<String> {
+        self.dropped_parents.clone()
+    }
+
+    fn collapsed_into(&self) -> Option<String> {
+        self.collapsed_into.clone()
+    }
+}
+
+#[derive(Clone)]
+pub struct Signature {
+    name: String,
+    email: String,
+    seconds: i64,
+    offset_minutes: i32,
+}
+
+impl From<git2::Signature<'_>> for Signature {
+    fn from(signature: git2::Signature) -> Signature {
+        Signature {
+            name: signature.name().unwrap_or("").to_owned(),
+            email: signature.email().unwrap_or("").to_owned(),
+            seconds: signature.when().seconds(),
+            offset_minutes: signature.when().offset_minutes(),
+        }
+    }
+}
+
+#[graphql_object(context = Context)]
+impl Signature {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn email(&self) -> &str {
+        &self.email
+    }
+
+    fn time(&self, format: Option<String>) -> String {
+        let dt = chrono::DateTime::<chrono::FixedOffset>::from_utc(
+            chrono::NaiveDateTime::from_timestamp(self.seconds, 0),
+            chrono::FixedOffset::east(self.offset_minutes * 60),
+        );
+        dt.format(&format.unwrap_or("%Y-%m-%dT%H:%M:%S%:z".to_string()))
+            .to_string()
+    }
+
+    fn timezone(&self) -> String {
+        let sign = if self.offset_minutes < 0 { "-" } else { "+" };
+        let offset = self.offset_minutes.abs();
+        format!("{}{:02}{:02}", sign, offset / 60, offset % 60)
+    }
+}
+
+pub struct Trailer {
+    key: String,
+    value: String,
+}
+
+#[graphql_object(context = Context)]
+impl Trailer {
+    fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// Trailers are the `Key: value` lines of the last paragraph of a commit message
+fn parse_trailers(message: &str) -> Vec<Trailer> {
+    let paragraphs = message.trim_end().split("\n\n").collect::<Vec<_>>();
+    if paragraphs.len() < 2 {
+        return vec![];
+    }
+    let last = paragraphs[paragraphs.len() - 1];
+
+    let mut trailers = vec![];
+    for line in last.lines() {
+        let mut split = line.splitn(2, ": ");
+        match (split.next(), split.next()) {
+            (Some(key), Some(value))
+                if !key.is_empty()
+                    && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') =>
+            {
+                trailers.push(Trailer {
+                    key: key.to_string(),
+                    value: value.trim().to_string(),
+                });
+            }
+            _ => return vec![],
+        }
+    }
+    return trailers;
 }
 
 pub struct Warning {
@@ -263,8 +958,8 @@ pub struct Path {
 
 pub fn linecount(repo: &git2::Repository, id: git2::Oid) -> usize {
     if let Ok(blob) = repo.find_blob(id) {
-        return blob.content().iter().filter(|x| **x == '\n' as u8).count()
-            + if blob.content().len() == 0 { 0 } else { 1 };
+        return blob.content().iter().filter(|x| **x == b'\n').count()
+            + if blob.content().is_empty() { 0 } else { 1 };
     }
 
     if let Ok(tree) = repo.find_tree(id) {
@@ -284,72 +979,129 @@ struct Markers {
     topic: String,
 }
 
-#[graphql_object(context = Context)]
 impl Markers {
-    fn data(&self, context: &Context) -> FieldResult<Vec<Document>> {
-        let transaction = context.transaction.lock()?;
-
-        let refname = transaction.refname("refs/josh/meta");
+    fn original_path(
+        &self,
+        transaction: &cache::Transaction,
+        path: &std::path::Path,
+    ) -> JoshResult<std::path::PathBuf> {
+        if self.filter == filter::nop() {
+            return Ok(path.to_owned());
+        }
+        let t = transaction.repo().find_commit(self.commit_id)?.tree()?;
+        let o = filter::tree::original_path(transaction, self.filter, t, path)?;
+        return Ok(std::path::PathBuf::from(o));
+    }
 
-        let r = transaction.repo().revparse_single(&refname);
-        let tree = if let Ok(r) = r {
-            let commit = transaction.repo().find_commit(r.id())?;
-            commit.tree()?
+    /// Markers of the file at `path`, including the ones attached to up to
+    /// `ancestors` first parent ancestors, with their line moved to where it
+    /// is in this revision
+    fn lookup(
+        &self,
+        transaction: &cache::Transaction,
+        path: &std::path::Path,
+        ancestors: i32,
+    ) -> JoshResult<Vec<Document>> {
+        let repo = transaction.repo();
+        let (mtree, _) = meta_tree(transaction)?;
+        let path = self.original_path(transaction, path)?;
+
+        let mut commit = repo.find_commit(self.commit_id)?;
+        let mut markers = read_markers(
+            repo,
+            &mtree,
+            &marker_path(&commit.id().to_string(), &self.topic).join(&path),
+        )?
+        .iter()
+        .map(|x| parse_marker(x))
+        .collect::<Vec<_>>();
+
+        let blob = if let Ok(e) = commit.tree()?.get_path(&path) {
+            e.id()
         } else {
-            filter::tree::empty(&transaction.repo())
+            return Ok(markers);
         };
 
-        let commit = self.commit_id.to_string();
+        let mut seen = markers
+            .iter()
+            .map(|x| x.id)
+            .collect::<std::collections::HashSet<_>>();
 
-        let path = if self.filter == filter::nop() {
-            marker_path(&commit, &self.topic).join(&self.path)
-        } else {
-            let t = transaction.repo().find_commit(self.commit_id)?.tree()?;
-            let o = filter::tree::original_path(&transaction, self.filter, t, &self.path)?;
-            marker_path(&commit, &self.topic).join(&o)
-        };
+        for _ in 0..ancestors {
+            commit = if let Ok(parent) = commit.parent(0) {
+                parent
+            } else {
+                break;
+            };
+            let lines = read_markers(
+                repo,
+                &mtree,
+                &marker_path(&commit.id().to_string(), &self.topic).join(&path),
+            )?;
+            if lines.is_empty() {
+                continue;
+            }
+            let hunks = if let Ok(e) = commit.tree()?.get_path(&path) {
+                line_hunks(repo, e.id(), blob)?
+            } else {
+                continue;
+            };
+            for line in lines {
+                let mut marker = parse_marker(&line);
+                if seen.insert(marker.id) {
+                    move_marker(&mut marker, &hunks);
+                    markers.push(marker);
+                }
+            }
+        }
 
-        let prev = if let Ok(e) = tree.get_path(&path) {
-            let blob = transaction.repo().find_blob(e.id())?;
-            std::str::from_utf8(blob.content())?.to_owned()
-        } else {
-            "".to_owned()
-        };
+        return Ok(markers);
+    }
+}
 
-        let lines = prev
-            .split("\n")
-            .filter(|x| *x != "")
-            .map(|x| {
-                let mut s = x.splitn(2, ":");
-                Document {
-                    id: s
-                        .next()
-                        .and_then(|x| git2::Oid::from_str(x).ok())
-                        .unwrap_or(git2::Oid::zero()),
-                    value: s
-                        .next()
-                        .and_then(|x| serde_json::from_str::<serde_json::Value>(x).ok())
-                        .unwrap_or_default()
-                        .to_owned(),
-                }
-            })
+#[graphql_object(context = Context)]
+impl Markers {
+    fn data(
+        &self,
+        conditions: Option<Vec<MarkerCondition>>,
+        ancestors: Option<i32>,
+        context: &Context,
+    ) -> FieldResult<Vec<Document>> {
+        let transaction = context.transaction()?;
+        let conditions = conditions.unwrap_or_default();
+        let lines = self
+            .lookup(&transaction, &self.path, ancestors.unwrap_or(0))?
+            .into_iter()
+            .filter(|x| marker_matches(x, &conditions))
             .collect::<Vec<_>>();
 
         Ok(lines)
     }
 
-    fn count(&self, context: &Context) -> FieldResult<i32> {
-        let transaction = context.transaction.lock()?;
+    fn count(&self, ancestors: Option<i32>, context: &Context) -> FieldResult<i32> {
+        let transaction = context.transaction()?;
 
-        let refname = transaction.refname("refs/josh/meta");
+        if let Some(ancestors) = ancestors.filter(|x| *x > 0) {
+            let commit = transaction.repo().find_commit(self.commit_id)?;
+            let tree = filter::apply(&transaction, self.filter, commit.tree()?)?;
+            let paths = match tree.get_path(&self.path).map(|x| x.kind()) {
+                Ok(Some(git2::ObjectType::Blob)) => vec![self.path.clone()],
+                _ => find_paths(
+                    &transaction,
+                    tree,
+                    Some(self.path.to_string_lossy().to_string()),
+                    None,
+                    git2::ObjectType::Blob,
+                )?,
+            };
+            let mut c = 0;
+            for path in paths {
+                c += self.lookup(&transaction, &path, ancestors)?.len();
+            }
+            return Ok(c as i32);
+        }
 
-        let r = transaction.repo().revparse_single(&refname);
-        let mtree = if let Ok(r) = r {
-            let commit = transaction.repo().find_commit(r.id())?;
-            commit.tree()?
-        } else {
-            filter::tree::empty(&transaction.repo())
-        };
+        let (mtree, _) = meta_tree(&transaction)?;
 
         let commit = self.commit_id.to_string();
         let mtree = mtree
@@ -380,13 +1132,32 @@ impl Markers {
     }
 }
 
+pub struct Marker {
+    path: String,
+    data: Document,
+}
+
+#[graphql_object(context = Context)]
+impl Marker {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn data(&self) -> Document {
+        Document {
+            id: self.data.id,
+            value: self.data.value.clone(),
+        }
+    }
+}
+
 impl Path {
     fn internal_serialize<R>(
         &self,
         context: &Context,
         to_result: impl FnOnce(&cache::Transaction, git2::Oid) -> FieldResult<R>,
     ) -> FieldResult<R> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let id = transaction
             .repo()
             .find_tree(self.tree)?
@@ -447,6 +1218,83 @@ impl Path {
     fn hash(&self, context: &Context) -> FieldResult<String> {
         self.internal_serialize(context, |_transaction, id| Ok(format!("{}", id)))
     }
+
+    fn blame(&self, context: &Context) -> FieldResult<Vec<BlameRange>> {
+        let transaction = context.transaction()?;
+        let commit = transaction.repo().find_commit(self.commit_id)?;
+        let filtered = filter::apply_to_commit(self.filter, &commit, &transaction)?;
+
+        let mut opts = git2::BlameOptions::new();
+        opts.newest_commit(filtered);
+        let blame = transaction.repo().blame_file(&self.path, Some(&mut opts))?;
+
+        let mut originals = std::collections::HashMap::new();
+        let mut ranges = vec![];
+        for hunk in blame.iter() {
+            let id = hunk.final_commit_id();
+            let original = if let Some(original) = originals.get(&id) {
+                *original
+            } else {
+                let original =
+                    history::find_original(&transaction, self.filter, self.commit_id, id)?;
+                originals.insert(id, original);
+                original
+            };
+            let start = hunk.final_start_line() as i32;
+            ranges.push(BlameRange {
+                start,
+                end: start + hunk.lines_in_hunk() as i32 - 1,
+                filtered: id,
+                original,
+                filter: self.filter,
+            });
+        }
+        Ok(ranges)
+    }
+
+    fn patch(
+        &self,
+        against: Option<String>,
+        filter: Option<String>,
+        context_lines: Option<i32>,
+        context: &Context,
+    ) -> FieldResult<Option<String>> {
+        let transaction = context.transaction()?;
+        let repo = transaction.repo();
+        let other_filter = if let Some(filter) = filter {
+            filter::parse(&filter)?
+        } else {
+            self.filter
+        };
+        let other = if let Some(against) = against {
+            Some(repo.find_commit(resolve_commit(&transaction, &against)?)?)
+        } else {
+            repo.find_commit(self.commit_id)?.parents().next()
+        };
+        let old_tree = if let Some(other) = other {
+            filter::apply(&transaction, other_filter, other.tree()?)?
+        } else {
+            filter::tree::empty(repo)
+        };
+
+        let entry_id = |tree: &git2::Tree| {
+            tree.get_path(&self.path)
+                .map(|x| x.id())
+                .unwrap_or(git2::Oid::zero())
+        };
+        let old_id = entry_id(&old_tree);
+        let new_id = entry_id(&repo.find_tree(self.tree)?);
+        if old_id == new_id {
+            return Ok(None);
+        }
+
+        Ok(blob_patch(
+            repo,
+            (old_id, &self.path),
+            (new_id, &self.path),
+            context_lines,
+        )?)
+    }
     fn text(&self, context: &Context) -> FieldResult<Option<String>> {
         self.internal_serialize(context, |transaction, id| {
             let blob = transaction.repo().find_blob(id)?;
@@ -466,10 +1314,40 @@ impl Path {
         })
     }
 
-    fn yaml(&self, context: &Context) -> FieldResult<Document> {
-        self.serialize_to_serde_value(context, |blob| {
-            serde_yaml::from_str::<serde_json::Value>(blob)
-        })
+    fn yaml(&self, context: &Context) -> FieldResult<Document> {
+        self.serialize_to_serde_value(context, |blob| {
+            serde_yaml::from_str::<serde_json::Value>(blob)
+        })
+    }
+}
+
+pub struct BlameRange {
+    start: i32,
+    end: i32,
+    filtered: git2::Oid,
+    original: git2::Oid,
+    filter: filter::Filter,
+}
+
+#[graphql_object(context = Context)]
+impl BlameRange {
+    fn start(&self) -> i32 {
+        self.start
+    }
+
+    fn end(&self) -> i32 {
+        self.end
+    }
+
+    fn hash(&self) -> String {
+        format!("{}", self.filtered)
+    }
+
+    fn rev(&self) -> Revision {
+        Revision {
+            filter: self.filter,
+            commit_id: self.original,
+        }
     }
 }
 
@@ -549,20 +1427,67 @@ pub struct Reference {
     refname: String,
 }
 
-#[graphql_object(context = Context)]
+#[derive(juniper::GraphQLEnum, Clone, Copy, PartialEq, Debug)]
+pub enum RefKind {
+    Branch,
+    Tag,
+    Other,
+}
+
 impl Reference {
-    fn name(&self) -> FieldResult<String> {
+    fn reference(&self) -> JoshResult<String> {
         Ok(UpstreamRef::from_str(&self.refname)
             .ok_or(josh_error("not a ns"))?
             .reference)
     }
+}
+
+#[graphql_object(context = Context)]
+impl Reference {
+    fn name(&self) -> FieldResult<String> {
+        Ok(self.reference()?)
+    }
+
+    fn kind(&self) -> FieldResult<RefKind> {
+        let reference = self.reference()?;
+        Ok(if reference.starts_with("refs/heads/") {
+            RefKind::Branch
+        } else if reference.starts_with("refs/tags/") {
+            RefKind::Tag
+        } else {
+            RefKind::Other
+        })
+    }
+
+    fn tag(&self, context: &Context) -> FieldResult<Option<Tag>> {
+        let transaction = context.transaction()?;
+        let target = transaction
+            .repo()
+            .find_reference(&self.refname)?
+            .target()
+            .unwrap_or(git2::Oid::zero());
+
+        let tag = if let Ok(tag) = transaction.repo().find_tag(target) {
+            tag
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(Tag {
+            name: tag.name().unwrap_or("").to_string(),
+            message: tag.message().map(|x| x.to_string()),
+            tagger: tag.tagger().map(Signature::from),
+            target: format!("{}", tag.target_id()),
+        }))
+    }
 
     fn rev(&self, context: &Context, filter: Option<String>) -> FieldResult<Revision> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let commit_id = transaction
             .repo()
             .find_reference(&self.refname)?
-            .target()
+            .peel_to_commit()
+            .map(|x| x.id())
             .unwrap_or(git2::Oid::zero());
 
         Ok(Revision {
@@ -572,12 +1497,91 @@ impl Reference {
     }
 }
 
+pub struct Tag {
+    name: String,
+    message: Option<String>,
+    tagger: Option<Signature>,
+    target: String,
+}
+
+#[graphql_object(context = Context)]
+impl Tag {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    fn tagger(&self) -> Option<Signature> {
+        self.tagger.clone()
+    }
+
+    fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+/// Pushes `new`, a commit on top of `old` in the history filtered with the given filter,
+/// to a branch of the upstream repository like a push to the proxy would, and returns the
+/// pushed upstream commit.
+pub type PushFn = std::sync::Arc<
+    dyn Fn(&str, filter::Filter, git2::Oid, git2::Oid) -> JoshResult<git2::Oid> + Send + Sync,
+>;
+
+/// Returns a stream that yields whenever the upstream refs of the repository
+/// might have changed.
+pub type WatchFn = Box<dyn Fn() -> futures::stream::BoxStream<'static, ()> + Send + Sync>;
+
+/// Resolvers each take a transaction out of a pool, so they don't block each other.
 pub struct Context {
-    transaction: std::sync::Arc<std::sync::Mutex<cache::Transaction>>,
+    transaction: std::sync::Mutex<cache::Transaction>,
+    pool: std::sync::Mutex<Vec<cache::Transaction>>,
+    push: Option<PushFn>,
+    watch: Option<WatchFn>,
 }
 
 impl juniper::Context for Context {}
 
+impl Context {
+    fn transaction(&self) -> JoshResult<PooledTransaction> {
+        let pooled = self.pool.lock()?.pop();
+        let transaction = if let Some(t) = pooled {
+            t
+        } else {
+            self.transaction.lock()?.clone()?
+        };
+        Ok(PooledTransaction {
+            transaction: Some(transaction),
+            pool: &self.pool,
+        })
+    }
+}
+
+struct PooledTransaction<'a> {
+    transaction: Option<cache::Transaction>,
+    pool: &'a std::sync::Mutex<Vec<cache::Transaction>>,
+}
+
+impl<'a> std::ops::Deref for PooledTransaction<'a> {
+    type Target = cache::Transaction;
+
+    fn deref(&self) -> &cache::Transaction {
+        self.transaction
+            .as_ref()
+            .expect("transaction returned to pool")
+    }
+}
+
+impl<'a> Drop for PooledTransaction<'a> {
+    fn drop(&mut self) {
+        if let (Some(t), Ok(mut pool)) = (self.transaction.take(), self.pool.lock()) {
+            pool.push(t);
+        }
+    }
+}
+
 pub struct Repository {
     name: String,
 }
@@ -590,7 +1594,7 @@ fn marker_path(commit: &str, topic: &str) -> std::path::PathBuf {
         .join(&commit[..2])
         .join(&commit[2..5])
         .join(&commit[5..9])
-        .join(&commit)
+        .join(commit)
 }
 
 #[derive(juniper::GraphQLInputObject)]
@@ -605,15 +1609,295 @@ struct MarkerInput {
     text: String,
 }
 
-fn format_marker(input: &String) -> JoshResult<String> {
-    let value = serde_json::from_str::<serde_json::Value>(&input)?;
+#[derive(juniper::GraphQLInputObject)]
+struct MarkerIdsInput {
+    path: String,
+    ids: Vec<String>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+struct MarkerReplaceInput {
+    path: String,
+    id: String,
+    data: String,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+struct MarkerCondition {
+    at: String,
+    equals: Option<String>,
+    min: Option<i32>,
+    max: Option<i32>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+struct AuthorInput {
+    name: String,
+    email: String,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+struct FileChangeInput {
+    path: String,
+    content: Option<String>,
+    delete: Option<bool>,
+}
+
+fn format_marker(input: &str) -> JoshResult<String> {
+    let value = serde_json::from_str::<serde_json::Value>(input)?;
     let line = serde_json::to_string(&value)?;
     let hash = git2::Oid::hash_object(git2::ObjectType::Blob, line.as_bytes())?;
     Ok(format!("{}:{}", &hash, &line))
 }
 
+fn parse_marker(line: &str) -> Document {
+    let mut s = line.splitn(2, ":");
+    Document {
+        id: s
+            .next()
+            .and_then(|x| git2::Oid::from_str(x).ok())
+            .unwrap_or(git2::Oid::zero()),
+        value: s
+            .next()
+            .and_then(|x| serde_json::from_str::<serde_json::Value>(x).ok())
+            .unwrap_or_default(),
+    }
+}
+
+fn marker_matches(marker: &Document, conditions: &[MarkerCondition]) -> bool {
+    for condition in conditions {
+        let value = if let Some(value) = marker.value.pointer(&condition.at) {
+            value
+        } else {
+            return false;
+        };
+        if let Some(equals) = &condition.equals {
+            let matches = match value {
+                serde_json::Value::String(s) => s == equals,
+                v => &v.to_string() == equals,
+            };
+            if !matches {
+                return false;
+            }
+        }
+        if condition.min.is_some() || condition.max.is_some() {
+            let n = if let Some(n) = value.as_f64() {
+                n
+            } else {
+                return false;
+            };
+            if condition.min.map(|min| n < min as f64).unwrap_or(false) {
+                return false;
+            }
+            if condition.max.map(|max| n > max as f64).unwrap_or(false) {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+/// Changed line ranges between two blobs as
+/// `(old_start, old_lines, new_start, new_lines)`
+fn line_hunks(
+    repo: &git2::Repository,
+    old: git2::Oid,
+    new: git2::Oid,
+) -> JoshResult<Vec<(u32, u32, u32, u32)>> {
+    let mut opts = git2::DiffOptions::new();
+    opts.context_lines(0);
+    let patch = git2::Patch::from_blobs(
+        &repo.find_blob(old)?,
+        None,
+        &repo.find_blob(new)?,
+        None,
+        Some(&mut opts),
+    )?;
+    let mut hunks = vec![];
+    for i in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(i)?;
+        hunks.push((
+            hunk.old_start(),
+            hunk.old_lines(),
+            hunk.new_start(),
+            hunk.new_lines(),
+        ));
+    }
+    return Ok(hunks);
+}
+
+/// Move the line (`/L`) of a marker through `hunks`; markers on lines
+/// that were changed get flagged as `outdated`
+fn move_marker(marker: &mut Document, hunks: &[(u32, u32, u32, u32)]) {
+    let line = if let Some(line) = marker.value.pointer("/L").and_then(|x| x.as_u64()) {
+        line as i64
+    } else {
+        return;
+    };
+
+    let mut moved = line;
+    let mut outdated = false;
+    for (old_start, old_lines, new_start, new_lines) in hunks.iter() {
+        let (old_start, old_lines) = (*old_start as i64, *old_lines as i64);
+        let (new_start, new_lines) = (*new_start as i64, *new_lines as i64);
+        if old_lines == 0 {
+            if line <= old_start {
+                break;
+            }
+        } else if line < old_start {
+            break;
+        } else if line < old_start + old_lines {
+            moved = std::cmp::max(new_start, 1);
+            outdated = true;
+            break;
+        }
+        moved += new_lines - old_lines;
+    }
+
+    if let Some(value) = marker.value.as_object_mut() {
+        value.insert("L".to_string(), json!(moved));
+        if outdated {
+            value.insert("outdated".to_string(), json!(true));
+        }
+    }
+}
+
+fn meta_tree(transaction: &cache::Transaction) -> JoshResult<(git2::Tree, Option<git2::Commit>)> {
+    let rev = transaction.refname("refs/josh/meta");
+    if let Ok(r) = transaction.repo().revparse_single(&rev) {
+        let commit = transaction.repo().find_commit(r.id())?;
+        return Ok((commit.tree()?, Some(commit)));
+    }
+    return Ok((filter::tree::empty(transaction.repo()), None));
+}
+
+fn read_markers(
+    repo: &git2::Repository,
+    tree: &git2::Tree,
+    path: &std::path::Path,
+) -> JoshResult<Vec<String>> {
+    if let Ok(e) = tree.get_path(path) {
+        let blob = repo.find_blob(e.id())?;
+        return Ok(std::str::from_utf8(blob.content())?
+            .split("\n")
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_owned())
+            .collect());
+    }
+    return Ok(vec![]);
+}
+
+fn write_markers<'a>(
+    repo: &'a git2::Repository,
+    tree: &git2::Tree,
+    path: &std::path::Path,
+    mut lines: Vec<String>,
+) -> JoshResult<git2::Tree<'a>> {
+    lines.sort();
+    lines.dedup();
+
+    if lines.is_empty() {
+        return filter::tree::insert(repo, tree, path, git2::Oid::zero(), 0);
+    }
+
+    let blob = repo.blob(lines.join("\n").as_bytes())?;
+    return filter::tree::insert(repo, tree, path, blob, 0o0100644);
+}
+
+fn commit_markers(
+    transaction: &cache::Transaction,
+    tree: &git2::Tree,
+    parent: Option<git2::Commit>,
+) -> JoshResult<()> {
+    let signature = transaction.repo().signature()?;
+    transaction.repo().commit(
+        Some(&transaction.refname("refs/josh/meta")),
+        &signature,
+        &signature,
+        "marker",
+        tree,
+        &parent.as_ref().into_iter().collect::<Vec<_>>(),
+    )?;
+    return Ok(());
+}
+
 #[graphql_object(context = Context)]
 impl RepositoryMut {
+    async fn commit(
+        &self,
+        rev: String,
+        filter: Option<String>,
+        changes: Vec<FileChangeInput>,
+        message: String,
+        author: AuthorInput,
+        context: &Context,
+    ) -> FieldResult<String> {
+        let push = context
+            .push
+            .clone()
+            .ok_or(josh_error("pushing is not supported here"))?;
+        if !rev.starts_with("refs/heads/") || !git2::Reference::is_valid_name(&rev) {
+            return Err(josh_error(&format!(
+                "invalid rev {:?}: must be a branch like refs/heads/<branchname>",
+                rev
+            ))
+            .into());
+        }
+        let transaction = context.transaction.lock()?.clone()?;
+
+        // Filtering, unapplying and pushing can take a while, so don't do it on the executor
+        let pushed = tokio::task::spawn_blocking(move || -> FieldResult<git2::Oid> {
+            let repo = transaction.repo();
+
+            let filterobj = filter::parse(&filter.unwrap_or(":/".to_string()))?;
+            let original = repo
+                .find_reference(&transaction.refname(&rev))?
+                .peel_to_commit()?;
+            let filtered = filter::apply_to_commit(filterobj, &original, &transaction)?;
+            if filtered == git2::Oid::zero() {
+                return Err(
+                    josh_error(&format!("the filtered history of {} is empty", rev)).into(),
+                );
+            }
+            let filtered = repo.find_commit(filtered)?;
+
+            let mut tree = filtered.tree()?;
+            for change in changes {
+                let path = std::path::Path::new(&change.path);
+                if let Some(true) = change.delete {
+                    if tree.get_path(path).is_err() {
+                        return Err(josh_error(&format!("file not found: {}", change.path)).into());
+                    }
+                    tree = filter::tree::insert(repo, &tree, path, git2::Oid::zero(), 0)?;
+                } else if let Some(content) = change.content {
+                    let mode = tree
+                        .get_path(path)
+                        .map(|x| x.filemode())
+                        .unwrap_or(0o0100644);
+                    let blob = repo.blob(content.as_bytes())?;
+                    tree = filter::tree::insert(repo, &tree, path, blob, mode)?;
+                } else {
+                    return Err(josh_error("changes need either content or delete").into());
+                }
+            }
+
+            if tree.id() == filtered.tree_id() {
+                return Err(josh_error("nothing to commit").into());
+            }
+
+            // The commit is created in the filtered history and pushed from there, so it
+            // gets mapped back to upstream like any other push
+            let signature = git2::Signature::now(&author.name, &author.email)?;
+            let oid = repo.commit(None, &signature, &signature, &message, &tree, &[&filtered])?;
+
+            Ok(push(&rev, filterobj, filtered.id(), oid)?)
+        })
+        .await
+        .map_err(|e| josh_error(&format!("commit: {}", e)))??;
+
+        Ok(format!("{}", pushed))
+    }
+
     fn meta(
         &self,
         commit: String,
@@ -621,60 +1905,79 @@ impl RepositoryMut {
         add: Vec<MarkersInput>,
         context: &Context,
     ) -> FieldResult<bool> {
-        let transaction = context.transaction.lock()?;
-        let rev = transaction.refname("refs/josh/meta");
+        let transaction = context.transaction()?;
 
         transaction
             .repo()
             .find_commit(git2::Oid::from_str(&commit)?)?;
 
-        let r = transaction.repo().revparse_single(&rev);
-        let (tree, parent) = if let Ok(r) = r {
-            let commit = transaction.repo().find_commit(r.id())?;
-            let tree = commit.tree()?;
-            (tree, Some(commit))
-        } else {
-            (filter::tree::empty(&transaction.repo()), None)
-        };
-
-        let mut tree = tree;
+        let (mut tree, parent) = meta_tree(&transaction)?;
 
         for mm in add {
-            let path = mm.path;
-            let path = &marker_path(&commit, &topic).join(&path);
-            let prev = if let Ok(e) = tree.get_path(&path) {
-                let blob = transaction.repo().find_blob(e.id())?;
-                std::str::from_utf8(blob.content())?.to_owned()
-            } else {
-                "".to_owned()
-            };
-
-            let mm = mm
-                .data
-                .iter()
-                .map(format_marker)
-                .collect::<JoshResult<Vec<_>>>()?;
+            let path = &marker_path(&commit, &topic).join(&mm.path);
+            let mut lines = read_markers(transaction.repo(), &tree, path)?;
 
-            let mut lines = prev.split("\n").filter(|x| *x != "").collect::<Vec<_>>();
-            for marker in mm.iter() {
-                lines.push(marker);
+            for marker in mm.data.iter() {
+                lines.push(format_marker(marker)?);
             }
-            lines.sort();
-            lines.dedup();
 
-            let blob = transaction.repo().blob(&lines.join("\n").as_bytes())?;
+            tree = write_markers(transaction.repo(), &tree, path, lines)?;
+        }
+
+        commit_markers(&transaction, &tree, parent)?;
+
+        Ok(true)
+    }
+
+    fn meta_remove(
+        &self,
+        commit: String,
+        topic: String,
+        remove: Vec<MarkerIdsInput>,
+        context: &Context,
+    ) -> FieldResult<bool> {
+        let transaction = context.transaction()?;
+        let (mut tree, parent) = meta_tree(&transaction)?;
+
+        for mm in remove {
+            let path = &marker_path(&commit, &topic).join(&mm.path);
+            let lines = read_markers(transaction.repo(), &tree, path)?
+                .into_iter()
+                .filter(|x| !mm.ids.contains(&parse_marker(x).id.to_string()))
+                .collect();
 
-            tree = filter::tree::insert(transaction.repo(), &tree, &path, blob, 0o0100644)?;
+            tree = write_markers(transaction.repo(), &tree, path, lines)?;
         }
 
-        transaction.repo().commit(
-            Some(&rev),
-            &transaction.repo().signature()?,
-            &transaction.repo().signature()?,
-            "marker",
-            &tree,
-            &parent.as_ref().into_iter().collect::<Vec<_>>(),
-        )?;
+        commit_markers(&transaction, &tree, parent)?;
+
+        Ok(true)
+    }
+
+    fn meta_replace(
+        &self,
+        commit: String,
+        topic: String,
+        replace: Vec<MarkerReplaceInput>,
+        context: &Context,
+    ) -> FieldResult<bool> {
+        let transaction = context.transaction()?;
+        let (mut tree, parent) = meta_tree(&transaction)?;
+
+        for mm in replace {
+            let path = &marker_path(&commit, &topic).join(&mm.path);
+            let mut lines = read_markers(transaction.repo(), &tree, path)?;
+
+            let index = lines
+                .iter()
+                .position(|x| parse_marker(x).id.to_string() == mm.id)
+                .ok_or(josh_error(&format!("marker not found: {}", mm.id)))?;
+            lines[index] = format_marker(&mm.data)?;
+
+            tree = write_markers(transaction.repo(), &tree, path, lines)?;
+        }
+
+        commit_markers(&transaction, &tree, parent)?;
 
         Ok(true)
     }
@@ -687,7 +1990,7 @@ impl Repository {
     }
 
     fn refs(&self, context: &Context, pattern: Option<String>) -> FieldResult<Vec<Reference>> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let refname = format!(
             "refs/josh/upstream/{}.git/{}",
             to_ns(&self.name),
@@ -713,11 +2016,15 @@ impl Repository {
     fn rev(&self, context: &Context, at: String, filter: Option<String>) -> FieldResult<Revision> {
         let rev = format!("refs/josh/upstream/{}.git/{}", to_ns(&self.name), at);
 
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
         let commit_id = if let Ok(id) = git2::Oid::from_str(&at) {
             id
         } else {
-            transaction.repo().revparse_single(&rev)?.id()
+            transaction
+                .repo()
+                .revparse_single(&rev)?
+                .peel_to_commit()?
+                .id()
         };
 
         Ok(Revision {
@@ -727,6 +2034,156 @@ impl Repository {
     }
 }
 
+pub struct RefUpdate {
+    name: String,
+    before: Option<git2::Oid>,
+    after: Option<git2::Oid>,
+    filter: filter::Filter,
+}
+
+#[graphql_object(context = Context)]
+impl RefUpdate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn before(&self) -> Option<String> {
+        self.before.map(|x| format!("{}", x))
+    }
+
+    fn after(&self) -> Option<String> {
+        self.after.map(|x| format!("{}", x))
+    }
+
+    fn rev(&self) -> Option<Revision> {
+        self.after.map(|commit_id| Revision {
+            filter: self.filter,
+            commit_id,
+        })
+    }
+}
+
+type RefUpdateStream =
+    std::pin::Pin<Box<dyn futures::Stream<Item = FieldResult<RefUpdate>> + Send>>;
+
+fn ref_state(
+    transaction: &cache::Transaction,
+    glob: &str,
+) -> JoshResult<std::collections::BTreeMap<String, git2::Oid>> {
+    let mut state = std::collections::BTreeMap::new();
+    for reference in transaction.repo().references_glob(glob)? {
+        let r = reference?;
+        let name = r.name().ok_or(josh_error("reference without name"))?;
+        if let Ok(commit) = r.peel_to_commit() {
+            state.insert(name.to_string(), commit.id());
+        }
+    }
+    return Ok(state);
+}
+
+/// Compare the refs matching `glob` to `known`, and update it
+fn ref_updates(
+    transaction: &cache::Transaction,
+    glob: &str,
+    filter: filter::Filter,
+    known: &mut std::collections::BTreeMap<String, git2::Oid>,
+) -> JoshResult<Vec<FieldResult<RefUpdate>>> {
+    let state = ref_state(transaction, glob)?;
+    let mut updates = vec![];
+
+    for (refname, id) in state.iter() {
+        let before = known.get(refname).cloned();
+        if before == Some(*id) {
+            continue;
+        }
+        // Make sure the filtered commit is in the cache before anyone asks for it
+        filter::apply_to_commit(filter, &transaction.repo().find_commit(*id)?, transaction)?;
+        updates.push(Ok(RefUpdate {
+            name: refname.to_string(),
+            before,
+            after: Some(*id),
+            filter,
+        }));
+    }
+    for (refname, id) in known.iter() {
+        if !state.contains_key(refname) {
+            updates.push(Ok(RefUpdate {
+                name: refname.to_string(),
+                before: Some(*id),
+                after: None,
+                filter,
+            }));
+        }
+    }
+
+    *known = state;
+    return Ok(updates);
+}
+
+pub struct RepositorySubscription {
+    name: String,
+}
+
+#[juniper::graphql_subscription(context = Context)]
+impl RepositorySubscription {
+    async fn refs(
+        &self,
+        pattern: Option<String>,
+        filter: Option<String>,
+        context: &Context,
+    ) -> FieldResult<RefUpdateStream> {
+        let watch = context
+            .watch
+            .as_ref()
+            .ok_or(josh_error("subscriptions are not supported here"))?;
+        let transaction = context.transaction.lock()?.clone()?;
+        let filter = filter::parse(&filter.unwrap_or(":/".to_string()))?;
+        let prefix = format!("refs/josh/upstream/{}.git/", to_ns(&self.name));
+        let glob = format!(
+            "{}{}",
+            prefix,
+            pattern.unwrap_or("refs/heads/*".to_string())
+        );
+        let known = ref_state(&transaction, &glob)?;
+        let state = std::sync::Arc::new(std::sync::Mutex::new((transaction, known)));
+
+        let updates = watch()
+            .then(move |_| {
+                let state = state.clone();
+                let glob = glob.clone();
+                async move {
+                    // Filtering the updated refs can take a while, so don't do it on
+                    // the executor
+                    let updates = tokio::task::spawn_blocking(move || {
+                        let mut state = state.lock()?;
+                        let (transaction, known) = &mut *state;
+                        ref_updates(transaction, &glob, filter, known)
+                    })
+                    .await;
+                    match updates {
+                        Ok(updates) => updates,
+                        Err(e) => Err(josh_error(&format!("ref_updates: {}", e))),
+                    }
+                }
+            })
+            .flat_map(move |updates| {
+                let updates = updates
+                    .unwrap_or_else(|e| vec![Err(e.into())])
+                    .into_iter()
+                    .map(|u| {
+                        u.map(|mut u| {
+                            u.name = u.name.trim_start_matches(&prefix).to_string();
+                            u
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                futures::stream::iter(updates)
+            });
+
+        Ok(updates.boxed())
+    }
+}
+
 pub struct Query;
 
 #[graphql_object(context = Context)]
@@ -736,9 +2193,9 @@ impl Query {
     }
 
     fn repos(context: &Context, name: Option<String>) -> FieldResult<Vec<Repository>> {
-        let transaction = context.transaction.lock()?;
+        let transaction = context.transaction()?;
 
-        let refname = format!("refs/josh/upstream/*.git/refs/heads/*");
+        let refname = "refs/josh/upstream/*.git/refs/heads/*".to_string();
 
         let mut repos = vec![];
 
@@ -765,7 +2222,7 @@ impl Query {
 
 regex_parsed!(
     UpstreamRef,
-    r"refs/josh/upstream/(?P<ns>.*)[.]git/(?P<reference>refs/heads/.*)",
+    r"refs/josh/upstream/(?P<ns>.*)[.]git/(?P<reference>refs/.*)",
     [ns, reference]
 );
 
@@ -774,7 +2231,30 @@ pub type Schema =
 
 pub fn context(transaction: cache::Transaction) -> Context {
     Context {
-        transaction: std::sync::Arc::new(std::sync::Mutex::new(transaction)),
+        transaction: std::sync::Mutex::new(transaction),
+        pool: std::sync::Mutex::new(vec![]),
+        push: None,
+        watch: None,
+    }
+}
+
+/// Like `context`, but also allows mutations that push to the upstream repository
+pub fn context_with_push(transaction: cache::Transaction, push: PushFn) -> Context {
+    Context {
+        transaction: std::sync::Mutex::new(transaction),
+        pool: std::sync::Mutex::new(vec![]),
+        push: Some(push),
+        watch: None,
+    }
+}
+
+impl Context {
+    /// Allow subscriptions, which get notified by `watch`
+    pub fn with_watch(self, watch: WatchFn) -> Context {
+        Context {
+            watch: Some(watch),
+            ..self
+        }
     }
 }
 
@@ -796,13 +2276,79 @@ pub fn commit_schema(commit_id: git2::Oid) -> CommitSchema {
     )
 }
 
-pub type RepoSchema =
-    juniper::RootNode<'static, Repository, RepositoryMut, EmptySubscription<Context>>;
+pub type RepoSchema = juniper::RootNode<'static, Repository, RepositoryMut, RepositorySubscription>;
 
 pub fn repo_schema(name: String) -> RepoSchema {
     RepoSchema::new(
-        Repository { name },
+        Repository { name: name.clone() },
         RepositoryMut {},
-        EmptySubscription::new(),
+        RepositorySubscription { name },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use juniper::graphql_value;
+
+    #[tokio::test]
+    async fn subscription_test() {
+        let dir = std::env::temp_dir().join(format!("josh-subscription-{}", std::process::id()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        cache::load(repo.path()).unwrap();
+
+        let refname = "refs/josh/upstream/real_repo.git/refs/heads/master";
+        let commit = |contents: &[u8], parents: &[&git2::Commit]| {
+            let blob = repo.blob(contents).unwrap();
+            let mut sub = repo.treebuilder(None).unwrap();
+            sub.insert("file", blob, 0o100644).unwrap();
+            let mut root = repo.treebuilder(None).unwrap();
+            root.insert("sub", sub.write().unwrap(), 0o040000).unwrap();
+            let tree = repo.find_tree(root.write().unwrap()).unwrap();
+            let sig = git2::Signature::now("Josh", "josh@example.com").unwrap();
+            repo.commit(Some(refname), &sig, &sig, "commit", &tree, parents)
+                .unwrap()
+        };
+        let first = commit(b"first", &[]);
+
+        let (notify, watched) = futures::channel::mpsc::unbounded::<()>();
+        let watched = std::sync::Mutex::new(Some(watched));
+        let context = context(cache::Transaction::open(&dir, None).unwrap()).with_watch(Box::new(
+            move || watched.lock().unwrap().take().unwrap().boxed(),
+        ));
+        let schema = repo_schema("real_repo".to_string());
+
+        let (value, errors) = juniper::resolve_into_stream(
+            "subscription { refs(filter: \":/sub\") { name before after } }",
+            None,
+            &schema,
+            &juniper::Variables::new(),
+            &context,
+        )
+        .await
+        .unwrap();
+        assert!(errors.is_empty());
+        let mut refs = match value {
+            juniper::Value::Object(o) => match o.into_iter().next() {
+                Some((_, juniper::Value::Scalar(stream))) => stream,
+                _ => panic!("not a stream"),
+            },
+            _ => panic!("not an object"),
+        };
+
+        let second = commit(b"second", &[&repo.find_commit(first).unwrap()]);
+        notify.unbounded_send(()).unwrap();
+
+        assert_eq!(
+            refs.next().await.unwrap().unwrap(),
+            graphql_value!({
+                "name": "refs/heads/master",
+                "before": (first.to_string()),
+                "after": (second.to_string())
+            })
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -11,7 +11,7 @@ pub fn walk2(
         return Ok(());
     });
 
-    if transaction.known(filter, input) {
+    if transaction.known(filter, input)? {
         return Ok(());
     }
 
@@ -24,6 +24,7 @@ pub fn walk2(
         for k in known.iter() {
             walk.hide(*k)?;
         }
+        hide_cutoff(&mut walk, filter, transaction)?;
         walk
     };
 
@@ -34,10 +35,13 @@ pub fn walk2(
     );
     let mut n_commits = 0;
     let mut n_misses = transaction.misses();
+    let progress = transaction.progress();
 
     let walks = transaction.new_walk();
 
     for original_commit_id in walk {
+        transaction.check_cancelled()?;
+
         if !filter::apply_to_commit3(
             filter,
             &transaction.repo().find_commit(original_commit_id?)?,
@@ -47,6 +51,11 @@ pub fn walk2(
         }
 
         n_commits += 1;
+        if n_commits % 100 == 0 {
+            if let Some(progress) = &progress {
+                progress.walk(filter, n_commits, n_new);
+            }
+        }
         if n_commits % 1000 == 0 {
             log::debug!(
                 "{} {} commits filtered, {} misses",
@@ -64,12 +73,43 @@ pub fn walk2(
         n_commits,
         transaction.misses() - n_misses,
     );
+    if let Some(progress) = &progress {
+        progress.walk(filter, n_commits, n_new);
+    }
 
     transaction.end_walk();
 
     return Ok(());
 }
 
+/// Walk the history of all `missing` (filter, commit) pairs. With `cache::jobs()` > 1 the
+/// pairs are walked concurrently, see `cache::parallel`.
+pub fn walk_missing(
+    missing: Vec<(filter::Filter, git2::Oid)>,
+    transaction: &cache::Transaction,
+) -> JoshResult<()> {
+    // Commits in the history of another missing commit get filtered by its walk
+    let mut tips = vec![];
+    for (f, i) in missing.iter() {
+        let mut covered = false;
+        for (f2, i2) in missing.iter() {
+            // Missing commits that are not in the repository are skipped by `walk2`
+            let descendant = transaction.repo().graph_descendant_of(*i2, *i);
+            if f2 == f && descendant.unwrap_or(false) {
+                covered = true;
+                break;
+            }
+        }
+        if !covered {
+            tips.push((*f, *i));
+        }
+    }
+    cache::parallel(transaction, tips, |t, (f, i)| walk2(f, i, t))?;
+    return Ok(());
+}
+
+/// Find the most recent commit in the history of `contained_in` that filters to
+/// `filtered`, to be used as base when unapplying commits on top of `filtered`.
 fn find_unapply_base(
     transaction: &cache::Transaction,
     bm: &mut std::collections::HashMap<git2::Oid, git2::Oid>,
@@ -86,11 +126,25 @@ fn find_unapply_base(
     let contained_in_commit = transaction.repo().find_commit(contained_in)?;
     let oid = filter::apply_to_commit(filter, &contained_in_commit, transaction)?;
     if oid != git2::Oid::zero() {
-        bm.insert(contained_in, oid);
+        bm.insert(oid, contained_in);
     }
+    if oid == filtered {
+        return Ok(contained_in);
+    }
+
+    // The reverse index only knows the commits that introduced `filtered`, but the base
+    // should be the most recent commit that maps to it. Those are descendants of the
+    // indexed ones, so only the history up to them needs to be walked.
+    let indexed = find_indexed(transaction, filter, contained_in, filtered)?;
+
     let mut walk = transaction.repo().revwalk()?;
     walk.set_sorting(git2::Sort::TOPOLOGICAL)?;
     walk.push(contained_in)?;
+    for original in indexed.iter() {
+        for parent in transaction.repo().find_commit(*original)?.parent_ids() {
+            walk.hide(parent)?;
+        }
+    }
 
     for original in walk {
         let original = transaction.repo().find_commit(original?)?;
@@ -99,7 +153,6 @@ fn find_unapply_base(
             return Ok(original.id());
         }
     }
-
     return Ok(git2::Oid::zero());
 }
 
@@ -109,32 +162,268 @@ pub fn find_original(
     contained_in: git2::Oid,
     filtered: git2::Oid,
 ) -> super::JoshResult<git2::Oid> {
+    let originals = find_originals(transaction, filter, contained_in, &[filtered])?;
+    return Ok(originals
+        .get(&filtered)
+        .cloned()
+        .unwrap_or(git2::Oid::zero()));
+}
+
+/// Like `find_original` for several filtered commits at once. Commits that can't be
+/// found in the reverse index are searched for in a single walk of the history.
+/// Commits without an original are left out of the result.
+pub fn find_originals(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    contained_in: git2::Oid,
+    filtered: &[git2::Oid],
+) -> super::JoshResult<std::collections::HashMap<git2::Oid, git2::Oid>> {
+    let mut result = std::collections::HashMap::new();
     if contained_in == git2::Oid::zero() {
-        return Ok(git2::Oid::zero());
+        return Ok(result);
+    }
+
+    let mut missing = std::collections::HashSet::new();
+    for filtered in filtered {
+        let mut candidates = std::collections::HashSet::new();
+        for original in find_indexed(transaction, filter, contained_in, *filtered)? {
+            let original = transaction.repo().find_commit(original)?;
+            if !parent_filters_to(transaction, filter, &original, *filtered)? {
+                candidates.insert(original.id());
+            }
+        }
+        match closest(transaction, contained_in, &candidates)? {
+            Some(original) => {
+                result.insert(*filtered, original);
+            }
+            None => {
+                missing.insert(*filtered);
+            }
+        }
+    }
+    if missing.is_empty() {
+        return Ok(result);
     }
+
     let mut walk = transaction.repo().revwalk()?;
     walk.set_sorting(git2::Sort::TOPOLOGICAL)?;
     walk.push(contained_in)?;
 
     for original in walk {
         let original = transaction.repo().find_commit(original?)?;
-        if filtered == filter::apply_to_commit(filter, &original, transaction)? {
-            if original.parent_ids().count() == 1 {
-                let fp = filter::apply_to_commit(
-                    filter,
-                    &original.parents().next().unwrap(),
-                    transaction,
-                )?;
+        let filtered = filter::apply_to_commit(filter, &original, transaction)?;
+        if !missing.contains(&filtered) {
+            continue;
+        }
+        if parent_filters_to(transaction, filter, &original, filtered)? {
+            continue;
+        }
+        missing.remove(&filtered);
+        result.insert(filtered, original.id());
+        if missing.is_empty() {
+            break;
+        }
+    }
 
-                if fp == filtered {
-                    continue;
-                }
+    return Ok(result);
+}
+
+/// Pick the one of `candidates` with the fewest parent links between it and `tip`,
+/// preferring earlier parents on a tie. The search is breadth first, so it only covers
+/// the history up to the distance of the closest candidate.
+fn closest(
+    transaction: &cache::Transaction,
+    tip: git2::Oid,
+    candidates: &std::collections::HashSet<git2::Oid>,
+) -> JoshResult<Option<git2::Oid>> {
+    if candidates.len() < 2 {
+        return Ok(candidates.iter().next().cloned());
+    }
+    let mut seen = std::collections::HashSet::new();
+    let mut queue = std::collections::VecDeque::new();
+    queue.push_back(tip);
+    while let Some(id) = queue.pop_front() {
+        if candidates.contains(&id) {
+            return Ok(Some(id));
+        }
+        for parent in transaction.repo().find_commit(id)?.parent_ids() {
+            if seen.insert(parent) {
+                queue.push_back(parent);
             }
-            return Ok(original.id());
         }
     }
+    return Ok(None);
+}
 
-    return Ok(git2::Oid::zero());
+/// True if `commit` has a single parent that also filters to `filtered`,
+/// meaning `commit` did not introduce the filtered commit.
+fn parent_filters_to(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    commit: &git2::Commit,
+    filtered: git2::Oid,
+) -> JoshResult<bool> {
+    if commit.parent_ids().count() != 1 {
+        return Ok(false);
+    }
+    let fp = filter::apply_to_commit(filter, &commit.parents().next().unwrap(), transaction)?;
+    return Ok(fp == filtered);
+}
+
+/// Use the reverse index to find commits contained in `contained_in`
+/// that filter to `filtered`.
+fn find_indexed(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    contained_in: git2::Oid,
+    filtered: git2::Oid,
+) -> JoshResult<Vec<git2::Oid>> {
+    let mut found = vec![];
+    for original in filter::originals(filter, filtered, transaction) {
+        if original != contained_in
+            && !transaction
+                .repo()
+                .graph_descendant_of(contained_in, original)
+                .unwrap_or(false)
+        {
+            continue;
+        }
+        let commit = ok_or!(transaction.repo().find_commit(original), {
+            continue;
+        });
+        if filtered == filter::apply_to_commit(filter, &commit, transaction)? {
+            found.push(original);
+        }
+    }
+    return Ok(found);
+}
+
+/// Combine the trees resulting from unapplying a merge commit onto each of its
+/// original parents. The merge base of the parents is used as ancestor, with the
+/// filtered part replaced as well, so conflicts can only happen outside of the filter.
+/// In that case the conflicting paths are returned.
+fn merge_unapplied<'a>(
+    transaction: &'a cache::Transaction,
+    filterobj: filter::Filter,
+    tree: &git2::Tree<'a>,
+    original_parents: &[&git2::Commit],
+    new_trees: &[git2::Oid],
+) -> JoshResult<std::result::Result<git2::Tree<'a>, Vec<String>>> {
+    let repo = transaction.repo();
+    let parent_ids: Vec<_> = original_parents.iter().map(|x| x.id()).collect();
+    let base_tree = match repo.merge_base_many(&parent_ids) {
+        Ok(base) => repo.find_commit(base)?.tree()?,
+        Err(_) => filter::tree::empty(repo),
+    };
+    let base_tree = filter::unapply(transaction, filterobj, tree.clone(), base_tree)?;
+
+    let mut merged = repo.find_tree(new_trees[0])?;
+    for new_tree in &new_trees[1..] {
+        let mut index = repo.merge_trees(&base_tree, &merged, &repo.find_tree(*new_tree)?, None)?;
+        if index.has_conflicts() {
+            return Ok(Err(conflicting_paths(&index)?));
+        }
+        merged = repo.find_tree(index.write_tree_to(repo)?)?;
+    }
+    return Ok(Ok(merged));
+}
+
+fn conflicting_paths(index: &git2::Index) -> JoshResult<Vec<String>> {
+    return Ok(index
+        .conflicts()?
+        .filter_map(|c| c.ok())
+        .filter_map(|c| c.our.or(c.their).or(c.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect());
+}
+
+/// Replay the commits reachable from `tip` but not from `onto` on top of `onto`.
+/// As long as the part of the tree inside of `filter` did not change between the old
+/// and the new base, the rest of the tree is simply taken from the new base. Otherwise
+/// the commit gets cherry-picked, and the conflicting paths are returned if that fails.
+pub fn rebase(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    tip: git2::Oid,
+    onto: git2::Oid,
+) -> JoshResult<std::result::Result<git2::Oid, Vec<String>>> {
+    let repo = transaction.repo();
+    if tip == onto || repo.graph_descendant_of(tip, onto)? {
+        return Ok(Ok(tip));
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(git2::Sort::REVERSE | git2::Sort::TOPOLOGICAL)?;
+    walk.push(tip)?;
+    walk.hide(onto)?;
+
+    let mut rewritten = std::collections::HashMap::<git2::Oid, git2::Oid>::new();
+    let mut ret = onto;
+    for rev in walk {
+        let commit = repo.find_commit(rev?)?;
+
+        // Only the replayed parents and the old base are replaced, other parents of
+        // merges are already part of the upstream history and kept as they are.
+        let mut new_parent_ids = vec![];
+        for (i, parent) in commit.parent_ids().enumerate() {
+            let id = match rewritten.get(&parent) {
+                Some(id) => *id,
+                None if i == 0 => onto,
+                None => parent,
+            };
+            if !new_parent_ids.contains(&id) {
+                new_parent_ids.push(id);
+            }
+        }
+        if new_parent_ids.is_empty() {
+            new_parent_ids.push(onto);
+        }
+        let new_parents = new_parent_ids
+            .iter()
+            .map(|id| repo.find_commit(*id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let new_parents_refs: Vec<_> = new_parents.iter().collect();
+
+        let old_base = match commit.parents().next() {
+            Some(parent) => parent.tree()?,
+            None => filter::tree::empty(repo),
+        };
+        let new_base = new_parents[0].tree()?;
+
+        let view_unchanged = filter::apply(transaction, filter, old_base.clone())?.id()
+            == filter::apply(transaction, filter, new_base.clone())?.id();
+
+        let new_tree = if view_unchanged {
+            let filtered = filter::apply(transaction, filter, commit.tree()?)?;
+            filter::unapply(transaction, filter, filtered, new_base)?
+        } else {
+            let mut index = repo.merge_trees(&old_base, &new_base, &commit.tree()?, None)?;
+            if index.has_conflicts() {
+                return Ok(Err(conflicting_paths(&index)?));
+            }
+            repo.find_tree(index.write_tree_to(repo)?)?
+        };
+
+        ret = rewrite_commit(repo, &commit, &new_parents_refs, &new_tree)?;
+        rewritten.insert(commit.id(), ret);
+    }
+
+    return Ok(Ok(ret));
+}
+
+/// Filters that cut off history never look below the cutoff commit, so
+/// there is no need to walk its ancestors.
+fn hide_cutoff(
+    walk: &mut git2::Revwalk,
+    filter: filter::Filter,
+    transaction: &cache::Transaction,
+) -> JoshResult<()> {
+    if let Some(since) = filter::cutoff(filter) {
+        for parent in transaction.repo().find_commit(since)?.parent_ids() {
+            walk.hide(parent)?;
+        }
+    }
+    return Ok(());
 }
 
 fn find_known(
@@ -146,20 +435,116 @@ fn find_known(
     let mut known = vec![];
     let mut walk = transaction.repo().revwalk()?;
     walk.push(input)?;
+    hide_cutoff(&mut walk, filter, transaction)?;
 
+    let mut error = None;
     let n_new = walk
-        .with_hide_callback(&|id| {
-            let k = transaction.known(filter, id);
-            if k {
-                known.push(id)
+        .with_hide_callback(&|id| match transaction.known(filter, id) {
+            Ok(k) => {
+                if k {
+                    known.push(id)
+                }
+                k
+            }
+            Err(e) => {
+                error = Some(e);
+                true
             }
-            k
         })?
         .count();
+    if let Some(e) = error {
+        return Err(e);
+    }
     log::debug!("/find_known {}", n_new);
     return Ok((known, n_new));
 }
 
+/// List the filtered commit for every commit in the history of `input`, parents first.
+/// Commits that are not part of the filtered history map to the zero id.
+/// With `changed_only` only commits that created a new filtered commit are listed,
+/// leaving out the ones that got collapsed into the filtered commit of a parent.
+pub fn commit_map(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    input: git2::Oid,
+    changed_only: bool,
+) -> JoshResult<Vec<(git2::Oid, git2::Oid)>> {
+    let repo = transaction.repo();
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(git2::Sort::REVERSE | git2::Sort::TOPOLOGICAL)?;
+    walk.push(input)?;
+
+    let mut mapped = std::collections::HashMap::<git2::Oid, git2::Oid>::new();
+    let mut result = vec![];
+    for id in walk {
+        let commit = repo.find_commit(id?)?;
+        let filtered = filter::apply_to_commit(filter, &commit, transaction)?;
+        mapped.insert(commit.id(), filtered);
+
+        if changed_only {
+            let collapsed = commit
+                .parent_ids()
+                .any(|parent| mapped.get(&parent) == Some(&filtered));
+            if filtered == git2::Oid::zero() || collapsed {
+                continue;
+            }
+        }
+        result.push((commit.id(), filtered));
+    }
+    return Ok(result);
+}
+
+/// What to do with the signatures of commits that get rewritten
+#[derive(Clone, Debug)]
+pub enum SignaturePolicy {
+    /// Keep the signatures of commits that don't change, drop all others
+    Preserve,
+    /// Drop all signatures, also from commits that don't change otherwise
+    Strip,
+    /// Sign rewritten commits with the SSH key in the given file
+    Resign(std::path::PathBuf),
+}
+
+lazy_static! {
+    static ref TAG_MESSAGE_TEMPLATE: std::sync::RwLock<Option<String>> =
+        std::sync::RwLock::new(None);
+    static ref SIGNATURE_POLICY: std::sync::RwLock<SignaturePolicy> =
+        std::sync::RwLock::new(SignaturePolicy::Preserve);
+}
+
+static INVALIDATED_SIGNATURES: std::sync::atomic::AtomicUsize =
+    std::sync::atomic::AtomicUsize::new(0);
+
+/// Changing the policy changes the ids of filtered commits, so the cache keeps
+/// separate results for every policy.
+pub fn set_signature_policy(policy: SignaturePolicy) {
+    *SIGNATURE_POLICY.write().unwrap() = policy;
+}
+
+/// Number of signed commits that have been rewritten without a signature by this process.
+/// Results taken from the cache are not counted, intermediate results of nested filters are.
+pub fn invalidated_signatures() -> usize {
+    INVALIDATED_SIGNATURES.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Key identifying non-default signature policies in the names of cache trees,
+/// as they change the ids of filtered commits.
+pub fn signature_policy_key() -> Option<String> {
+    return match &*SIGNATURE_POLICY.read().unwrap() {
+        SignaturePolicy::Preserve => None,
+        SignaturePolicy::Strip => Some("signatures=strip".to_string()),
+        SignaturePolicy::Resign(key) => Some(format!("signatures=resign:{}", key.display())),
+    };
+}
+
+/// Check for a signature in the commit header, without asking libgit2 to extract it
+fn is_signed(commit: &git2::Commit) -> bool {
+    return commit
+        .raw_header_bytes()
+        .split(|c| *c == b'\n')
+        .any(|line| line.starts_with(b"gpgsig"));
+}
+
 // takes everything from base except it's tree and replaces it with the tree
 // given
 pub fn rewrite_commit(
@@ -168,23 +553,353 @@ pub fn rewrite_commit(
     parents: &[&git2::Commit],
     tree: &git2::Tree,
 ) -> JoshResult<git2::Oid> {
-    if base.tree()?.id() == tree.id() && all_equal(base.parents(), parents) {
+    return rewrite_commit_with(repo, base, parents, tree, &SignaturePolicy::Preserve);
+}
+
+/// Like `rewrite_commit`, but applying the configured signature policy. Only used for
+/// commits created by filtering, changes written back with `unapply` or `rebase` are
+/// never resigned.
+pub fn rewrite_filtered_commit(
+    repo: &git2::Repository,
+    base: &git2::Commit,
+    parents: &[&git2::Commit],
+    tree: &git2::Tree,
+) -> JoshResult<git2::Oid> {
+    let policy = SIGNATURE_POLICY.read().unwrap().clone();
+    return rewrite_commit_with(repo, base, parents, tree, &policy);
+}
+
+fn rewrite_commit_with(
+    repo: &git2::Repository,
+    base: &git2::Commit,
+    parents: &[&git2::Commit],
+    tree: &git2::Tree,
+    policy: &SignaturePolicy,
+) -> JoshResult<git2::Oid> {
+    let unchanged = base.tree()?.id() == tree.id() && all_equal(base.parents(), parents);
+
+    if unchanged && !matches!(policy, SignaturePolicy::Strip) {
         // Looks like an optimization, but in fact serves to not change the commit in case
         // it was signed.
         return Ok(base.id());
     }
 
+    let signed = is_signed(base);
+    if unchanged && !signed {
+        return Ok(base.id());
+    }
+
     let b = repo.commit_create_buffer(
         &base.author(),
         &base.committer(),
-        &base.message_raw().unwrap_or("no message"),
+        base.message_raw().unwrap_or("no message"),
         tree,
         parents,
     )?;
 
+    if let SignaturePolicy::Resign(key) = policy {
+        let content = b.as_str().ok_or(josh_error("commit is not valid utf-8"))?;
+        let signature = ssh_sign(key, content)?;
+        return Ok(repo.commit_signed(content, &signature, None)?);
+    }
+
+    if signed {
+        INVALIDATED_SIGNATURES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
     return Ok(repo.odb()?.write(git2::ObjectType::Commit, &b)?);
 }
 
+fn ssh_sign(key: &std::path::Path, content: &str) -> JoshResult<String> {
+    use std::io::Write;
+
+    let mut child = std::process::Command::new("ssh-keygen")
+        .args(["-Y", "sign", "-n", "git", "-f"])
+        .arg(key)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .ok_or(josh_error("can't write to ssh-keygen"))?
+        .write_all(content.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(josh_error(&format!(
+            "ssh-keygen failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    return Ok(String::from_utf8(output.stdout)?);
+}
+
+/// Set a template for the messages of filtered annotated tags. `{message}`, `{filter}`
+/// and `{original}` get replaced with the original message, the filter spec and the id
+/// of the original tagged commit.
+pub fn set_tag_message_template(template: Option<String>) {
+    *TAG_MESSAGE_TEMPLATE.write().unwrap() = template;
+}
+
+/// Create an annotated tag like `tag`, but pointing to `target`.
+/// A signature of the original tag is dropped, as it would not be valid anymore.
+pub fn rewrite_tag(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    tag: &git2::Tag,
+    target: git2::Oid,
+) -> JoshResult<git2::Oid> {
+    if tag.target_id() == target {
+        return Ok(tag.id());
+    }
+
+    let repo = transaction.repo();
+    let target = repo.find_object(target, None)?;
+
+    let message = tag.message().unwrap_or("");
+    let message = [
+        "-----BEGIN PGP SIGNATURE-----",
+        "-----BEGIN SSH SIGNATURE-----",
+    ]
+    .iter()
+    .filter_map(|marker| message.find(*marker))
+    .min()
+    .map(|end| &message[..end])
+    .unwrap_or(message);
+
+    let message = match TAG_MESSAGE_TEMPLATE.read().unwrap().as_ref() {
+        Some(template) => format!(
+            "{}\n",
+            fill_template(
+                template,
+                &[
+                    ("{message}", message.trim_end()),
+                    ("{filter}", &filter::spec(filter)),
+                    ("{original}", &tag.target_id().to_string()),
+                ]
+            )
+        ),
+        None => message.to_string(),
+    };
+
+    // Tags without a tagger are rare, but do exist in old repos
+    let tagger = match tag.tagger() {
+        Some(tagger) => tagger.to_owned(),
+        None => target.peel_to_commit()?.committer().to_owned(),
+    };
+
+    // git2 has no way to create a tag object without also creating a ref for it
+    let raw = format!(
+        "object {}\ntype {}\ntag {}\ntagger {}\n\n{}",
+        target.id(),
+        target.kind().map(|k| k.str()).unwrap_or("commit"),
+        tag.name().unwrap_or(""),
+        format_signature(&tagger),
+        message
+    );
+    return Ok(repo.odb()?.write(git2::ObjectType::Tag, raw.as_bytes())?);
+}
+
+/// Replace the `placeholders` in `template` in a single pass, so text inserted for one
+/// placeholder never gets replaced again.
+fn fill_template(template: &str, placeholders: &[(&str, &str)]) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    'outer: while !rest.is_empty() {
+        for (placeholder, value) in placeholders {
+            if rest.starts_with(placeholder) {
+                result.push_str(value);
+                rest = &rest[placeholder.len()..];
+                continue 'outer;
+            }
+        }
+        let c = rest.chars().next().unwrap();
+        result.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    return result;
+}
+
+/// Format a signature the way it is stored in commit and tag headers.
+fn format_signature(signature: &git2::Signature) -> String {
+    let when = signature.when();
+    let offset = when.offset_minutes();
+    return format!(
+        "{} <{}> {} {}{:02}{:02}",
+        String::from_utf8_lossy(signature.name_bytes()),
+        String::from_utf8_lossy(signature.email_bytes()),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    );
+}
+
+/// Read the notes stored in the tree of `notes_commit`, indexed by annotated object.
+fn notes_map(
+    repo: &git2::Repository,
+    notes_commit: git2::Oid,
+) -> JoshResult<std::collections::BTreeMap<git2::Oid, git2::Oid>> {
+    let mut notes = std::collections::BTreeMap::new();
+    repo.find_commit(notes_commit)?
+        .tree()?
+        .walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                // Notes can be stored with a fanout, like "ab/cdef..."
+                let name = format!("{}{}", root.replace("/", ""), entry.name().unwrap_or(""));
+                if let Ok(annotated) = git2::Oid::from_str(&name) {
+                    notes.insert(annotated, entry.id());
+                }
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+    return Ok(notes);
+}
+
+/// Filter the history of the notes ref at `notes_commit`: Every notes commit is replaced by
+/// one with the notes attached to the filtered commits instead, and the filtered version of
+/// its first parent as parent. Notes of commits that don't introduce a filtered commit are
+/// dropped, as are notes commits that don't change any of the remaining notes.
+pub fn filter_notes(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    notes_commit: git2::Oid,
+) -> JoshResult<git2::Oid> {
+    let repo = transaction.repo();
+
+    let mut todo = vec![];
+    let mut parent = None;
+    let mut current = Some(notes_commit);
+    while let Some(oid) = current {
+        if let Some(filtered) = transaction.get_ref(filter, oid) {
+            parent = Some(filtered);
+            break;
+        }
+        todo.push(oid);
+        current = repo.find_commit(oid)?.parent_ids().next();
+    }
+
+    // Oldest first, so the parent is always filtered already
+    for oid in todo.into_iter().rev() {
+        let filtered = filter_notes_commit(transaction, filter, oid, parent)?;
+        transaction.insert_ref(filter, oid, filtered);
+        parent = Some(filtered);
+    }
+
+    return parent.ok_or(josh_error("filter_notes: nothing to filter"));
+}
+
+fn filter_notes_commit(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    notes_commit: git2::Oid,
+    parent: Option<git2::Oid>,
+) -> JoshResult<git2::Oid> {
+    let repo = transaction.repo();
+    let mut builder = repo.treebuilder(None)?;
+    for (annotated, note) in notes_map(repo, notes_commit)? {
+        let commit = ok_or!(repo.find_commit(annotated), {
+            continue;
+        });
+        let filtered = filter::apply_to_commit(filter, &commit, transaction)?;
+        if filtered == git2::Oid::zero()
+            || parent_filters_to(transaction, filter, &commit, filtered)?
+        {
+            continue;
+        }
+        builder.insert(filtered.to_string(), note, 0o100644)?;
+    }
+    let tree = repo.find_tree(builder.write()?)?;
+
+    let parent = match parent {
+        Some(parent) => Some(repo.find_commit(parent)?),
+        None => None,
+    };
+    if let Some(parent) = &parent {
+        if parent.tree_id() == tree.id() {
+            return Ok(parent.id());
+        }
+    }
+    let parents: Vec<_> = parent.iter().collect();
+    return rewrite_filtered_commit(repo, &repo.find_commit(notes_commit)?, &parents, &tree);
+}
+
+/// Apply the changes between the filtered notes commits `old` and `new` to the notes in
+/// `upstream`, attaching them to the original commits contained in `contained_in`.
+/// All changes end up in a single notes commit on top of `upstream`. Returns `None` if
+/// there is nothing to push.
+pub fn unapply_notes(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    upstream: Option<git2::Oid>,
+    old: git2::Oid,
+    new: git2::Oid,
+    contained_in: &[git2::Oid],
+) -> JoshResult<Option<git2::Oid>> {
+    let repo = transaction.repo();
+    let old_notes = if old != git2::Oid::zero() {
+        notes_map(repo, old)?
+    } else {
+        std::collections::BTreeMap::new()
+    };
+    let new_notes = notes_map(repo, new)?;
+    let upstream_notes = match upstream {
+        Some(upstream) => notes_map(repo, upstream)?,
+        None => std::collections::BTreeMap::new(),
+    };
+
+    let original_of = |filtered: git2::Oid| -> JoshResult<git2::Oid> {
+        for head in contained_in {
+            let original = find_original(transaction, filter, *head, filtered)?;
+            if original != git2::Oid::zero() {
+                return Ok(original);
+            }
+        }
+        return Err(josh_error(&format!(
+            "can't find the original commit of {}",
+            filtered
+        )));
+    };
+
+    let mut notes = upstream_notes.clone();
+    for (filtered, note) in new_notes.iter() {
+        if old_notes.get(filtered) != Some(note) {
+            notes.insert(original_of(*filtered)?, *note);
+        }
+    }
+    for filtered in old_notes.keys() {
+        if !new_notes.contains_key(filtered) {
+            // The note might not exist upstream anymore, which is fine
+            notes.remove(&original_of(*filtered)?);
+        }
+    }
+
+    if notes == upstream_notes {
+        return Ok(upstream);
+    }
+
+    let mut builder = repo.treebuilder(None)?;
+    for (annotated, note) in notes {
+        builder.insert(annotated.to_string(), note, 0o100644)?;
+    }
+    let tree = repo.find_tree(builder.write()?)?;
+
+    let parent = match upstream {
+        Some(upstream) => Some(repo.find_commit(upstream)?),
+        None => None,
+    };
+    let parents: Vec<_> = parent.iter().collect();
+    return Ok(Some(rewrite_filtered_commit(
+        repo,
+        &repo.find_commit(new)?,
+        &parents,
+        &tree,
+    )?));
+}
+
 fn all_equal(a: git2::Parents, b: &[&git2::Commit]) -> bool {
     let a: Vec<_> = a.collect();
     if a.len() != b.len() {
@@ -199,6 +914,7 @@ fn all_equal(a: git2::Parents, b: &[&git2::Commit]) -> bool {
     return true;
 }
 
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(skip(transaction))]
 pub fn unapply_filter(
     transaction: &cache::Transaction,
@@ -217,7 +933,7 @@ pub fn unapply_filter(
         let mut walk = transaction.repo().revwalk()?;
         walk.set_sorting(git2::Sort::REVERSE | git2::Sort::TOPOLOGICAL)?;
         walk.push(new)?;
-        if let Ok(_) = walk.hide(old) {
+        if walk.hide(old).is_ok() {
             tracing::trace!("walk: hidden {}", old);
         } else {
             tracing::warn!("walk: can't hide");
@@ -240,10 +956,10 @@ pub fn unapply_filter(
         let mut filtered_parent_ids: Vec<_> = module_commit.parent_ids().collect();
 
         let is_initial_merge = filtered_parent_ids.len() == 2
-            && !transaction
+            && transaction
                 .repo()
                 .merge_base_many(&filtered_parent_ids)
-                .is_ok();
+                .is_err();
 
         if !keep_orphans && is_initial_merge {
             filtered_parent_ids.pop();
@@ -252,7 +968,7 @@ pub fn unapply_filter(
         let original_parents: std::result::Result<Vec<_>, _> = filtered_parent_ids
             .iter()
             .map(|x| -> JoshResult<_> {
-                find_unapply_base(&transaction, &mut bm, filterobj, original_target, *x)
+                find_unapply_base(transaction, &mut bm, filterobj, original_target, *x)
             })
             .filter(|x| {
                 if let Ok(i) = x {
@@ -323,20 +1039,32 @@ pub fn unapply_filter(
                     transaction,
                     filterobj,
                     tree,
-                    filter::tree::empty(&transaction.repo()),
+                    filter::tree::empty(transaction.repo()),
                 )?
             }
-            parent_count => {
+            _ => {
                 // This is a merge commit where the parents in the upstream repo
                 // have differences outside of the current filter.
-                // It is unclear what base tree to pick in this case.
-                tracing::warn!("rejecting merge");
-                return Ok(UnapplyResult::RejectMerge(parent_count));
+                // Those get combined with a three-way merge.
+                let new_trees: Vec<_> = new_trees.into_iter().collect();
+                match merge_unapplied(
+                    transaction,
+                    filterobj,
+                    &tree,
+                    &original_parents_refs,
+                    &new_trees,
+                )? {
+                    Ok(merged) => merged,
+                    Err(conflicts) => {
+                        tracing::warn!("rejecting merge");
+                        return Ok(UnapplyResult::RejectMerge(conflicts));
+                    }
+                }
             }
         };
 
         ret = rewrite_commit(
-            &transaction.repo(),
+            transaction.repo(),
             &module_commit,
             &original_parents_refs,
             &new_tree,
@@ -359,10 +1087,10 @@ pub fn unapply_filter(
                     ));
                 }
 
-                let merged_tree = merged_index.write_tree_to(&transaction.repo())?;
+                let merged_tree = merged_index.write_tree_to(transaction.repo())?;
 
                 ret = rewrite_commit(
-                    &transaction.repo(),
+                    transaction.repo(),
                     &module_commit,
                     &original_parents_refs,
                     &transaction.repo().find_tree(merged_tree)?,
@@ -377,15 +1105,84 @@ pub fn unapply_filter(
     return Ok(UnapplyResult::Done(ret));
 }
 
+/// The decisions taken by `create_filtered_commit2` for one commit
+#[derive(Clone, Debug, Default)]
+pub struct Decisions {
+    /// Whether the filtered tree is the same as the ones of all filtered parents,
+    /// or empty for commits without filtered parents
+    pub tree_unchanged: bool,
+    /// Filtered parents dropped because the commit is an initial merge of unrelated histories
+    pub dropped_parents: Vec<git2::Oid>,
+    /// Filtered commit of a parent that was reused because no new commit was needed
+    pub collapsed_into: Option<git2::Oid>,
+}
+
+/// Why a commit is or is not part of a filtered history, as found by `explain`
+pub struct Explanation {
+    /// Filtered commit the original commit maps to, zero if it is not part of the history
+    pub filtered: git2::Oid,
+    /// Members of the filter that produced a different tree than for any of the parents
+    pub touched_by: Vec<filter::Filter>,
+    /// The decisions taken when the commit was filtered
+    pub decisions: Decisions,
+}
+
+/// Find out why `commit` is (or is not) part of the history filtered with `filter`.
+/// The commit is filtered again with the decisions of `create_filtered_commit2` being
+/// recorded, the history leading to it is taken from the cache.
+pub fn explain(
+    transaction: &cache::Transaction,
+    filter: filter::Filter,
+    commit: &git2::Commit,
+) -> JoshResult<Explanation> {
+    // Make sure the parents are filtered already
+    filter::apply_to_commit(filter, commit, transaction)?;
+
+    transaction.start_explain(commit.id());
+    let filtered = filter::apply_to_commit(filter, commit, transaction);
+    let decisions = transaction.finish_explain().unwrap_or_default();
+    let filtered = filtered?;
+
+    let mut touched_by = vec![];
+    for member in filter::members(filter) {
+        let tree = filter::apply(transaction, member, commit.tree()?)?.id();
+        let mut parent_trees = vec![];
+        for parent in commit.parents() {
+            parent_trees.push(filter::apply(transaction, member, parent.tree()?)?.id());
+        }
+        let touched = if parent_trees.is_empty() {
+            tree != filter::tree::empty_id()
+        } else {
+            parent_trees.iter().any(|x| *x != tree)
+        };
+        if touched {
+            touched_by.push(member);
+        }
+    }
+
+    return Ok(Explanation {
+        filtered,
+        touched_by,
+        decisions,
+    });
+}
+
 fn select_parent_commits<'a>(
     original_commit: &'a git2::Commit,
     filtered_tree_id: git2::Oid,
     filtered_parent_commits: Vec<&'a git2::Commit>,
+    decisions: &mut Decisions,
 ) -> Vec<&'a git2::Commit<'a>> {
     let affects_filtered = filtered_parent_commits
         .iter()
         .any(|x| filtered_tree_id != x.tree_id());
 
+    decisions.tree_unchanged = if filtered_parent_commits.is_empty() {
+        filtered_tree_id == filter::tree::empty_id()
+    } else {
+        !affects_filtered
+    };
+
     let all_diffs_empty = original_commit
         .parents()
         .all(|x| x.tree_id() == original_commit.tree_id());
@@ -404,16 +1201,19 @@ pub fn create_filtered_commit<'a>(
     transaction: &cache::Transaction,
     filter: filter::Filter,
 ) -> JoshResult<git2::Oid> {
+    let mut decisions = Decisions::default();
     let (r, is_new) = create_filtered_commit2(
-        &transaction.repo(),
+        transaction.repo(),
         original_commit,
         filtered_parent_ids,
         filtered_tree,
+        &mut decisions,
     )?;
+    transaction.record_decisions(original_commit.id(), decisions);
 
     let store = is_new || original_commit.parent_ids().len() != 1;
 
-    transaction.insert(filter, original_commit.id(), r, store);
+    transaction.insert(filter, original_commit.id(), r, store)?;
 
     return Ok(r);
 }
@@ -423,6 +1223,7 @@ fn create_filtered_commit2<'a>(
     original_commmit: &'a git2::Commit,
     filtered_parent_ids: Vec<git2::Oid>,
     filtered_tree: git2::Tree<'a>,
+    decisions: &mut Decisions,
 ) -> JoshResult<(git2::Oid, bool)> {
     let filtered_parent_commits: std::result::Result<Vec<_>, _> = filtered_parent_ids
         .iter()
@@ -437,24 +1238,31 @@ fn create_filtered_commit2<'a>(
         .any(|x| x.tree_id() == filter::tree::empty_id())
     {
         let is_initial_merge =
-            filtered_parent_ids.len() > 1 && !repo.merge_base_many(&filtered_parent_ids).is_ok();
+            filtered_parent_ids.len() > 1 && repo.merge_base_many(&filtered_parent_ids).is_err();
 
         if is_initial_merge {
+            decisions.dropped_parents = filtered_parent_commits
+                .iter()
+                .filter(|x| x.tree_id() == filter::tree::empty_id())
+                .map(|x| x.id())
+                .collect();
             filtered_parent_commits.retain(|x| x.tree_id() != filter::tree::empty_id());
         }
     }
 
     let selected_filtered_parent_commits: Vec<&_> = select_parent_commits(
-        &original_commmit,
+        original_commmit,
         filtered_tree.id(),
         filtered_parent_commits.iter().collect(),
+        decisions,
     );
 
-    if selected_filtered_parent_commits.len() == 0
+    if selected_filtered_parent_commits.is_empty()
         && !(original_commmit.parents().len() == 0
-            && is_empty_root(&repo, &original_commmit.tree()?))
+            && is_empty_root(repo, &original_commmit.tree()?))
     {
-        if filtered_parent_commits.len() != 0 {
+        if !filtered_parent_commits.is_empty() {
+            decisions.collapsed_into = Some(filtered_parent_commits[0].id());
             return Ok((filtered_parent_commits[0].id(), false));
         }
         if filtered_tree.id() == filter::tree::empty_id() {
@@ -463,9 +1271,9 @@ fn create_filtered_commit2<'a>(
     }
 
     return Ok((
-        rewrite_commit(
-            &repo,
-            &original_commmit,
+        rewrite_filtered_commit(
+            repo,
+            original_commmit,
             &selected_filtered_parent_commits,
             &filtered_tree,
         )?,
@@ -481,8 +1289,8 @@ fn is_empty_root(repo: &git2::Repository, tree: &git2::Tree) -> bool {
     let mut all_empty = true;
 
     for e in tree.iter() {
-        if let Ok(Ok(t)) = e.to_object(&repo).map(|x| x.into_tree()) {
-            all_empty = all_empty && is_empty_root(&repo, &t);
+        if let Ok(Ok(t)) = e.to_object(repo).map(|x| x.into_tree()) {
+            all_empty = all_empty && is_empty_root(repo, &t);
         } else {
             return false;
         }
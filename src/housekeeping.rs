@@ -18,20 +18,21 @@ pub fn default_from_to(
     for glob in [
         format!("refs/josh/upstream/{}/refs/heads/*", &to_ns(upstream_repo)),
         format!("refs/josh/upstream/{}/refs/tags/*", &to_ns(upstream_repo)),
+        format!("refs/josh/upstream/{}/refs/notes/*", &to_ns(upstream_repo)),
     ]
     .iter()
     {
         for refname in repo.references_glob(glob).unwrap().names() {
             let refname = refname.unwrap();
             let to_ref = refname.replacen("refs/josh/upstream", "refs/namespaces", 1);
-            let to_ref = to_ref.replacen(&to_ns(upstream_repo), &namespace, 1);
+            let to_ref = to_ref.replacen(&to_ns(upstream_repo), namespace, 1);
             refs.push((refname.to_owned(), to_ref.clone()));
         }
     }
     refs.append(&mut memorize_from_to(
-        &repo,
-        &crate::to_filtered_ref(&upstream_repo, &filter_spec),
-        &upstream_repo,
+        repo,
+        &crate::to_filtered_ref(upstream_repo, filter_spec),
+        upstream_repo,
     ));
 
     return refs;
@@ -95,7 +96,7 @@ pub fn discover_filter_candidates(transaction: &cache::Transaction) -> JoshResul
     let trace_s = span!(Level::TRACE, "discover_filter_candidates");
     let _e = trace_s.enter();
 
-    let refname = format!("refs/josh/upstream/*.git/refs/heads/*");
+    let refname = "refs/josh/upstream/*.git/refs/heads/*".to_string();
 
     for reference in repo.references_glob(&refname)? {
         let r = reference?;
@@ -109,14 +110,11 @@ pub fn discover_filter_candidates(transaction: &cache::Transaction) -> JoshResul
         let hs = find_all_workspaces_and_subdirectories(&r.peel_to_tree()?)?;
 
         for i in hs {
-            known_filters
-                .entry(name.clone())
-                .or_insert_with(BTreeSet::new)
-                .insert(i);
+            known_filters.entry(name.clone()).or_default().insert(i);
         }
     }
 
-    let refname = format!("josh/filtered/*.git/*/refs/heads/*");
+    let refname = "josh/filtered/*.git/*/refs/heads/*".to_string();
     for reference in repo.references_glob(&refname)? {
         let r = reference?;
         let name = r.name().ok_or(josh_error("reference without name"))?;
@@ -125,7 +123,7 @@ pub fn discover_filter_candidates(transaction: &cache::Transaction) -> JoshResul
 
         known_filters
             .entry(from_ns(&filtered.upstream_repo))
-            .or_insert_with(BTreeSet::new)
+            .or_default()
             .insert(from_ns(&filtered.filter_spec));
     }
 
@@ -138,10 +136,10 @@ pub fn find_all_workspaces_and_subdirectories(
     let _trace_s = span!(Level::TRACE, "find_all_workspaces_and_subdirectories");
     let mut hs = std::collections::HashSet::new();
     tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
-        if entry.name() == Some(&"workspace.josh") {
+        if entry.name() == Some("workspace.josh") {
             hs.insert(format!(":workspace={}", root.trim_matches('/')));
         }
-        if root == "" {
+        if root.is_empty() {
             return 0;
         }
         let v = format!(":/{}", root.trim_matches('/'));
@@ -163,13 +161,13 @@ pub fn get_info(
 
     let obj = transaction
         .repo()
-        .revparse_single(&transaction.refname(&headref))?;
+        .revparse_single(&transaction.refname(headref))?;
 
     let commit = obj.peel_to_commit()?;
 
     let mut meta = std::collections::HashMap::new();
     meta.insert("sha1".to_owned(), "".to_owned());
-    let filtered = filter::apply_to_commit(filter, &commit, &transaction)?;
+    let filtered = filter::apply_to_commit(filter, &commit, transaction)?;
 
     let parent_ids = |commit: &git2::Commit| {
         commit
@@ -217,6 +215,7 @@ pub fn refresh_known_filters(
 ) -> JoshResult<usize> {
     for (upstream_repo, e) in known_filters.iter() {
         let t = transaction.clone()?;
+        t.set_track_access(false);
         info!("background rebuild root: {:?}", upstream_repo);
 
         let mut updated_count = 0;
@@ -225,9 +224,9 @@ pub fn refresh_known_filters(
             tracing::trace!("background rebuild: {:?} {:?}", upstream_repo, filter_spec);
 
             let refs = memorize_from_to(
-                &t.repo(),
-                &to_filtered_ref(&upstream_repo, &filter_spec),
-                &upstream_repo,
+                t.repo(),
+                &to_filtered_ref(upstream_repo, filter_spec),
+                upstream_repo,
             );
 
             updated_count += filter_refs(&t, filter::parse(filter_spec)?, &refs)?;
@@ -237,26 +236,52 @@ pub fn refresh_known_filters(
     return Ok(0);
 }
 
-pub fn run(repo_path: &std::path::Path, do_gc: bool) -> JoshResult<()> {
+fn remove_filtered_refs(repo: &git2::Repository, filter_spec: &str) -> JoshResult<()> {
+    let glob = format!("refs/josh/filtered/*/{}/*", to_ns(filter_spec));
+    for reference in repo.references_glob(&glob)? {
+        let mut reference = reference?;
+        reference.delete()?;
+    }
+    Ok(())
+}
+
+/// Drop the cache of `filter` and stop housekeeping from rebuilding it.
+/// Returns `true` if anything was cached for the filter.
+pub fn drop_filter(transaction: &cache::Transaction, filter: filter::Filter) -> JoshResult<bool> {
+    remove_filtered_refs(transaction.repo(), &filter::spec(filter))?;
+    cache::drop_filter(filter)
+}
+
+pub fn run(
+    repo_path: &std::path::Path,
+    do_gc: bool,
+    retention: Option<std::time::Duration>,
+) -> JoshResult<()> {
     let transaction = cache::Transaction::open(repo_path, None)?;
+    if let Some(retention) = retention {
+        for filter_spec in cache::evict(retention)? {
+            info!("evicted unused filter: {:?}", filter_spec);
+            remove_filtered_refs(transaction.repo(), &filter_spec)?;
+        }
+    }
     let known_filters = housekeeping::discover_filter_candidates(&transaction)?;
     refresh_known_filters(&transaction, &known_filters)?;
     info!(
         "{}",
-        run_command(transaction.repo().path(), &"git count-objects -v").replace("\n", "  ")
+        run_command(transaction.repo().path(), "git count-objects -v").replace("\n", "  ")
     );
     if do_gc {
         info!(
             "\n----------\n{}\n----------",
-            run_command(transaction.repo().path(), &"git repack -adkbn --threads=1")
+            run_command(transaction.repo().path(), "git repack -adkbn --threads=1")
         );
         info!(
             "\n----------\n{}\n----------",
-            run_command(transaction.repo().path(), &"git count-objects -vH")
+            run_command(transaction.repo().path(), "git count-objects -vH")
         );
         info!(
             "\n----------\n{}\n----------",
-            run_command(transaction.repo().path(), &"git prune --expire=2w")
+            run_command(transaction.repo().path(), "git prune --expire=2w")
         );
     }
     Ok(())
@@ -1,5 +1,6 @@
 #![deny(warnings)]
 #![warn(unused_extern_crates)]
+#![allow(clippy::needless_return)]
 
 #[macro_export]
 macro_rules! some_or {
@@ -35,8 +36,6 @@ extern crate pest_derive;
 #[macro_use]
 extern crate serde_json;
 
-use tracing;
-
 pub mod cache;
 pub mod filter;
 pub mod graphql;
@@ -48,7 +47,7 @@ pub mod shell;
 #[derive(Clone)]
 pub enum UnapplyResult {
     Done(git2::Oid),
-    RejectMerge(usize),
+    RejectMerge(Vec<String>),
     RejectAmend(String),
     BranchDoesNotExist,
 }
@@ -81,8 +80,8 @@ pub fn from_ns(path: &str) -> String {
 pub fn to_filtered_ref(upstream_repo: &str, filter_spec: &str) -> String {
     return format!(
         "josh/filtered/{}/{}",
-        to_ns(&upstream_repo),
-        to_ns(&filter_spec)
+        to_ns(upstream_repo),
+        to_ns(filter_spec)
     );
 }
 
@@ -134,11 +133,7 @@ lazy_static! {
             .expect("can't compile regex");
 }
 
-        let caps = if let Some(caps) = REGEX.captures(&path) {
-            caps
-        } else {
-            return None;
-        };
+        let caps = REGEX.captures(&path)?;
 
         let as_str = |x: regex::Match| x.as_str().to_owned();
 
@@ -162,6 +157,36 @@ pub fn get_change_id(commit: &git2::Commit) -> Option<String> {
     return None;
 }
 
+/// True for references to git notes, also inside of `refs/josh/upstream/`
+pub fn is_notes_ref(refname: &str) -> bool {
+    return refname.starts_with("refs/notes/") || refname.contains("/refs/notes/");
+}
+
+fn filter_notes_ref(
+    transaction: &cache::Transaction,
+    filterobj: filter::Filter,
+    from_refsname: &str,
+    to_refname: &str,
+) -> JoshResult<usize> {
+    let notes = ok_or!(transaction.repo().refname_to_id(from_refsname), {
+        tracing::warn!("filter_ref: Can't find reference {:?}", &from_refsname);
+        return Ok(0);
+    });
+
+    let filtered = history::filter_notes(transaction, filterobj, notes)?;
+
+    let previous = transaction
+        .repo()
+        .refname_to_id(to_refname)
+        .unwrap_or(git2::Oid::zero());
+
+    transaction
+        .repo()
+        .reference(to_refname, filtered, true, "apply_filter")?;
+
+    return Ok(if filtered != previous { 1 } else { 0 });
+}
+
 #[tracing::instrument(skip(transaction))]
 fn filter_ref(
     transaction: &cache::Transaction,
@@ -169,8 +194,11 @@ fn filter_ref(
     from_refsname: &str,
     to_refname: &str,
 ) -> JoshResult<usize> {
+    if is_notes_ref(from_refsname) {
+        return filter_notes_ref(transaction, filterobj, from_refsname, to_refname);
+    }
     let mut updated_count = 0;
-    if let Ok(reference) = transaction.repo().revparse_single(&from_refsname) {
+    if let Ok(reference) = transaction.repo().revparse_single(from_refsname) {
         let original_commit = reference.peel_to_commit()?;
         let oid = original_commit.id();
 
@@ -179,22 +207,37 @@ fn filter_ref(
         } else {
             tracing::trace!("apply_to_commit");
 
-            filter::apply_to_commit(filterobj, &original_commit, &transaction)?
+            filter::apply_to_commit(filterobj, &original_commit, transaction)?
+        };
+
+        // Annotated tags get a filtered tag object of their own, so they keep their message
+        let target = match reference.as_tag() {
+            Some(tag) if filter_commit != git2::Oid::zero() => {
+                if let Some(s) = transaction.get_ref(filterobj, tag.id()) {
+                    s
+                } else {
+                    let filtered_tag =
+                        history::rewrite_tag(transaction, filterobj, tag, filter_commit)?;
+                    transaction.insert_ref(filterobj, tag.id(), filtered_tag);
+                    filtered_tag
+                }
+            }
+            _ => filter_commit,
         };
 
         let previous = transaction
             .repo()
-            .revparse_single(&to_refname)
+            .revparse_single(to_refname)
             .map(|x| x.id())
             .unwrap_or(git2::Oid::zero());
 
-        if filter_commit != previous {
+        if target != previous {
             updated_count += 1;
             tracing::trace!(
                 "filter_ref: update reference: {:?} -> {:?}, target: {:?}, filter: {:?}",
                 &from_refsname,
                 &to_refname,
-                filter_commit,
+                target,
                 &filter::spec(filterobj),
             );
         }
@@ -205,14 +248,14 @@ fn filter_ref(
             ok_or!(
                 transaction
                     .repo()
-                    .reference(&to_refname, filter_commit, true, "apply_filter")
+                    .reference(to_refname, target, true, "apply_filter")
                     .map(|_| ()),
                 {
                     tracing::error!(
                         "can't update reference: {:?} -> {:?}, target: {:?}, filter: {:?}",
                         &from_refsname,
                         &to_refname,
-                        filter_commit,
+                        target,
                         &filter::spec(filterobj),
                     );
                 }
@@ -224,6 +267,40 @@ fn filter_ref(
     return Ok(updated_count);
 }
 
+fn filter_ref_or_warn(
+    transaction: &cache::Transaction,
+    filterobj: filter::Filter,
+    from_refsname: &str,
+    to_refname: &str,
+) -> usize {
+    ok_or!(
+        filter_ref(transaction, filterobj, from_refsname, to_refname),
+        {
+            tracing::event!(
+                tracing::Level::WARN,
+                msg = "filter_refs: Can't filter reference",
+                warn = true,
+                from = from_refsname,
+                to = to_refname
+            );
+            0
+        }
+    )
+}
+
+fn report_ref_done(
+    transaction: &cache::Transaction,
+    done: &std::sync::atomic::AtomicUsize,
+    total: usize,
+) {
+    let n_refs = done.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+    if let Some(progress) = transaction.progress() {
+        progress.refs(n_refs, total);
+    }
+}
+
+/// Filter all `refs` given as `(from, to)` pairs. With `cache::jobs()` > 1 the references
+/// are processed on multiple threads, see `cache::parallel`.
 pub fn filter_refs(
     transaction: &cache::Transaction,
     filterobj: filter::Filter,
@@ -235,20 +312,16 @@ pub fn filter_refs(
 
     tracing::trace!("filter_refs");
 
-    let mut updated_count = 0;
-    for (k, v) in refs {
-        updated_count += ok_or!(filter_ref(&transaction, filterobj, &k, &v), {
-            tracing::event!(
-                tracing::Level::WARN,
-                msg = "filter_refs: Can't filter reference",
-                warn = true,
-                from = k.as_str(),
-                to = v.as_str()
-            );
-            0
-        });
-    }
-    return Ok(updated_count);
+    let total = refs.len();
+    let done = std::sync::atomic::AtomicUsize::new(0);
+
+    let updated = cache::parallel(transaction, refs.to_vec(), move |t, (k, v)| {
+        let updated_count = filter_ref_or_warn(t, filterobj, &k, &v);
+        report_ref_done(t, &done, total);
+        Ok(updated_count)
+    })?;
+    transaction.check_cancelled()?;
+    return Ok(updated.into_iter().sum());
 }
 
 pub fn normalize_path(path: &std::path::Path) -> std::path::PathBuf {
@@ -30,7 +30,7 @@ impl GraphQLHelper {
 
         let blob = tree
             .get_path(&path)?
-            .to_object(&transaction.repo())?
+            .to_object(transaction.repo())?
             .peel_to_blob()
             .map(|x| x.content().to_vec())
             .unwrap_or(vec![]);
@@ -75,7 +75,7 @@ impl handlebars::HelperDef for GraphQLHelper {
         return Ok(handlebars::ScopedJson::Derived(
             self.josh_helper(
                 h.hash(),
-                &rc.get_current_template_name().unwrap_or(&"/".to_owned()),
+                rc.get_current_template_name().unwrap_or(&"/".to_owned()),
             )
             .map_err(|_| handlebars::RenderError::new("josh"))?,
         ));
@@ -103,12 +103,12 @@ pub fn render(
     let path = split
         .next()
         .ok_or(josh_error(&format!("invalid query {:?}", query_and_params)))?;
-    let reference = repo.find_reference(&headref)?;
+    let reference = repo.find_reference(headref)?;
     let tree = reference.peel_to_tree()?;
 
     let obj = ok_or!(
         tree.get_path(&std::path::PathBuf::from(path))?
-            .to_object(&repo),
+            .to_object(repo),
         {
             return Ok(None);
         }
@@ -137,9 +137,9 @@ pub fn render(
             for (k, v) in params {
                 variables.insert(k.to_string(), juniper::InputValue::scalar(v));
             }
-            let transaction = cache::Transaction::open(&repo.path(), None)?;
+            let transaction = cache::Transaction::open(repo.path(), None)?;
             let (res, _errors) = juniper::execute_sync(
-                &template.to_string(),
+                template,
                 None,
                 &graphql::commit_schema(reference.target().ok_or(josh_error("missing target"))?),
                 &variables,
@@ -162,7 +162,7 @@ pub fn render(
     std::mem::drop(tree);
 
     let mut handlebars = handlebars::Handlebars::new();
-    handlebars.register_template_string(&path, template)?;
+    handlebars.register_template_string(path, template)?;
     handlebars.register_helper("concat", Box::new(helpers::concat_helper));
     handlebars.register_helper(
         "graphql",
@@ -174,8 +174,8 @@ pub fn render(
     );
     handlebars.set_strict_mode(true);
 
-    match handlebars.render(&path, &json!(params)) {
-        Ok(res) => return Ok(Some(format!("{}", res))),
+    match handlebars.render(path, &json!(params)) {
+        Ok(res) => return Ok(Some(res.to_string())),
         Err(res) => return Err(josh_error(&format!("{}", res))),
     }
 }
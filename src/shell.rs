@@ -20,6 +20,32 @@ impl Shell {
         cmd: &str,
         env: &[(&str, &str)],
         env_notrace: &[(&str, &str)],
+    ) -> (String, String, i32) {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(cmd);
+        return self.run(command, cmd, env, env_notrace);
+    }
+
+    /// Run `args[0]` with the remaining `args` as arguments. Unlike `command_env` this
+    /// does not go through `sh`, so the arguments are passed on as they are.
+    #[tracing::instrument(skip(self, env_notrace))]
+    pub fn command_args_env(
+        &self,
+        args: &[&str],
+        env: &[(&str, &str)],
+        env_notrace: &[(&str, &str)],
+    ) -> (String, String, i32) {
+        let mut command = Command::new(args[0]);
+        command.args(&args[1..]);
+        return self.run(command, &args.join(" "), env, env_notrace);
+    }
+
+    fn run(
+        &self,
+        mut command: Command,
+        cmd: &str,
+        env: &[(&str, &str)],
+        env_notrace: &[(&str, &str)],
     ) -> (String, String, i32) {
         let git_dir = if self.cwd.join(".git").exists() {
             self.cwd.join(".git")
@@ -27,19 +53,14 @@ impl Shell {
             self.cwd.to_path_buf()
         };
 
-        let mut command = Command::new("sh");
-        command
-            .current_dir(&self.cwd)
-            .arg("-c")
-            .arg(&cmd)
-            .env("GIT_DIR", &git_dir);
+        command.current_dir(&self.cwd).env("GIT_DIR", &git_dir);
 
         for (k, v) in env.iter() {
-            command.env(&k, &v);
+            command.env(k, v);
         }
 
         for (k, v) in env_notrace.iter() {
-            command.env(&k, &v);
+            command.env(k, v);
         }
 
         let output = command
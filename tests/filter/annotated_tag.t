@@ -0,0 +1,33 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+
+  $ mkdir sub2
+  $ echo contents1 > sub2/file2
+  $ git add sub2
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ git tag -a v1 -m "release 1"
+
+  $ josh-filter :/sub1 refs/tags/v1 --update refs/tags/v1_sub1
+  $ git for-each-ref --format="%(objecttype) %(taggername) %(subject) -> %(*subject)" refs/tags
+  tag Josh release 1 -> add file2
+  tag Josh release 1 -> add file1
+
+  $ josh-filter :/sub1 refs/tags/v1 --update refs/tags/v1_template --tag-message-template "{message} ({filter})"
+  $ git for-each-ref --format="%(objecttype) %(subject) -> %(*subject)" refs/tags/v1_template
+  tag release 1 (:/sub1) -> add file1
+
+Placeholders in the original message are kept as they are
+
+  $ git tag -a v2 -m "release {filter} {original}"
+  $ josh-filter :/sub1 refs/tags/v2 --update refs/tags/v2_template --tag-message-template "{message} ({filter})"
+  $ git for-each-ref --format="%(objecttype) %(subject) -> %(*subject)" refs/tags/v2_template
+  tag release {filter} {original} (:/sub1) -> add file1
@@ -0,0 +1,37 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+
+  $ mkdir sub2
+  $ echo contents1 > sub2/file2
+  $ git add sub2
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ echo contents3 > sub1/file3
+  $ git add sub1
+  $ git commit -m "add file3" 1> /dev/null
+
+  $ josh-filter :/sub1 master --map ${TESTTMP}/map
+  $ head -n 1 ${TESTTMP}/map
+  old                                      new
+  $ tail -n +2 ${TESTTMP}/map | wc -l | tr -d " "
+  3
+  $ grep "^$(git rev-parse master) " ${TESTTMP}/map | cut -d " " -f 2 > ${TESTTMP}/tip
+  $ test "$(cat ${TESTTMP}/tip)" = "$(git rev-parse FILTERED_HEAD)" && echo ok
+  ok
+
+The commit that only touched sub2 is collapsed into its parent
+
+  $ josh-filter :/sub1 master --map ${TESTTMP}/map_changed --map-changed-only
+  $ tail -n +2 ${TESTTMP}/map_changed | wc -l | tr -d " "
+  2
+  $ grep -c "^$(git rev-parse master~1) " ${TESTTMP}/map_changed
+  0
+  [1]
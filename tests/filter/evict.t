@@ -0,0 +1,30 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1 sub2
+  $ echo contents1 > sub1/file1
+  $ echo contents2 > sub2/file2
+  $ git add .
+  $ git commit -m "initial" 1> /dev/null
+
+  $ josh-filter :/sub1 master --update refs/heads/sub1
+  $ josh-filter :/sub2 master --update refs/heads/sub2
+
+Nothing is evicted while filters are in use
+
+  $ josh-filter --evict 3600
+
+  $ sleep 2
+  $ josh-filter :/sub2 master --update refs/heads/sub2
+
+  $ josh-filter --evict 1
+  evicted :/sub1
+
+The evicted filter is rebuilt on the next use
+
+  $ josh-filter :/sub1 master --update refs/heads/sub1_again
+  $ git rev-parse sub1 sub1_again | uniq | wc -l
+  1
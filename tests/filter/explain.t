@@ -0,0 +1,34 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub2
+  $ echo contents1 > sub2/file2
+  $ git add sub2
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+
+  $ echo contents2 > sub2/file2
+  $ git commit -a -m "change file2" 1> /dev/null
+
+  $ josh-filter ":[:/sub1,:/sub3]" --explain HEAD~2 | sed -E "s/[0-9a-f]{40}/<id>/g"
+  commit <id>
+  not part of the filtered history
+  filtered tree unchanged
+
+  $ josh-filter ":[:/sub1,:/sub3]" --explain HEAD~1 | sed -E "s/[0-9a-f]{40}/<id>/g"
+  commit <id>
+  filtered to <id>
+  filtered tree changed
+  touched by :/sub1
+
+  $ josh-filter ":[:/sub1,:/sub3]" --explain HEAD | sed -E "s/[0-9a-f]{40}/<id>/g"
+  commit <id>
+  collapsed into <id>
+  filtered tree unchanged
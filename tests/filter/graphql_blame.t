@@ -0,0 +1,63 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init repo 1> /dev/null
+  $ cd repo
+
+  $ mkdir sub1
+  $ printf "line1\nline2\n" > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+
+  $ mkdir sub2
+  $ echo contents2 > sub2/file2
+  $ git add sub2
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ printf "line1\nchanged\nline3\n" > sub1/file1
+  $ git commit -a -m "change file1" 1> /dev/null
+
+  $ cat > x.graphql <<EOF
+  > query {
+  >  rev(filter: ":/sub1") {
+  >   file(path: "file1") {
+  >    blame {
+  >     start
+  >     end
+  >     hash
+  >     rev {
+  >      summary
+  >     }
+  >    }
+  >   }
+  >  }
+  > }
+  > EOF
+  $ git add x.graphql
+  $ git commit -m "add query" 1> /dev/null
+
+  $ josh-filter -q "graphql=x.graphql"
+  {
+    "rev": {
+      "file": {
+        "blame": [
+          {
+            "start": 1,
+            "end": 1,
+            "hash": "5a9f6da7d71776b837398628d49302535b937fa4",
+            "rev": {
+              "summary": "add file1"
+            }
+          },
+          {
+            "start": 2,
+            "end": 3,
+            "hash": "f48139dd48539e9071ec17ac8e291d1026a41f05",
+            "rev": {
+              "summary": "change file1"
+            }
+          }
+        ]
+      }
+    }
+  } (no-eol)
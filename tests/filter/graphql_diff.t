@@ -0,0 +1,132 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init repo 1> /dev/null
+  $ cd repo
+
+  $ mkdir sub1 sub2
+  $ echo contents1 > sub1/file1
+  $ echo contents5 > sub1/file5
+  $ echo contents2 > sub2/file2
+  $ git add .
+  $ git commit -m "initial" 1> /dev/null
+
+  $ echo changed1 > sub1/file1
+  $ echo contents3 > sub1/file3
+  $ git rm -q sub1/file5
+  $ git mv sub2/file2 sub2/file4
+  $ printf '\000\001\002' > sub1/bin
+
+  $ cat > x.graphql <<EOF
+  > query {
+  >  sub1: rev(filter: ":/sub1") {
+  >   diff(against: "HEAD~1") {
+  >    path
+  >    oldPath
+  >    status
+  >   }
+  >   changes: diff(against: "HEAD~1") {
+  >    path
+  >    patch
+  >   }
+  >   file(path: "file1") {
+  >    unchanged: patch(against: "HEAD")
+  >    changed: patch(against: "HEAD~1", contextLines: 0)
+  >   }
+  >  }
+  >  sub2: rev(filter: ":/sub2") {
+  >   diff(against: "HEAD~1") {
+  >    path
+  >    oldPath
+  >    status
+  >   }
+  >  }
+  >  other: rev(filter: ":/sub1") {
+  >   diff(against: "HEAD", filter: ":/sub2") {
+  >    path
+  >    status
+  >   }
+  >  }
+  > }
+  > EOF
+  $ git add .
+  $ git commit -m "change" 1> /dev/null
+
+  $ josh-filter -q "graphql=x.graphql"
+  {
+    "sub1": {
+      "diff": [
+        {
+          "path": "bin",
+          "oldPath": null,
+          "status": "ADDED"
+        },
+        {
+          "path": "file1",
+          "oldPath": null,
+          "status": "MODIFIED"
+        },
+        {
+          "path": "file3",
+          "oldPath": null,
+          "status": "ADDED"
+        },
+        {
+          "path": "file5",
+          "oldPath": null,
+          "status": "DELETED"
+        }
+      ],
+      "changes": [
+        {
+          "path": "bin",
+          "patch": null
+        },
+        {
+          "path": "file1",
+          "patch": "diff --git a/file1 b/file1\n*--- a/file1\n+++ b/file1\n@@ -1 +1 @@\n-contents1\n+changed1\n" (glob)
+        },
+        {
+          "path": "file3",
+          "patch": "diff --git a/file3 b/file3\n*--- /dev/null\n+++ b/file3\n@@ -0,0 +1 @@\n+contents3\n" (glob)
+        },
+        {
+          "path": "file5",
+          "patch": "diff --git a/file5 b/file5\n*--- a/file5\n+++ /dev/null\n@@ -1 +0,0 @@\n-contents5\n" (glob)
+        }
+      ],
+      "file": {
+        "unchanged": null,
+        "changed": "diff --git a/file1 b/file1\n*--- a/file1\n+++ b/file1\n@@ -1 +1 @@\n-contents1\n+changed1\n" (glob)
+      }
+    },
+    "sub2": {
+      "diff": [
+        {
+          "path": "file4",
+          "oldPath": "file2",
+          "status": "RENAMED"
+        }
+      ]
+    },
+    "other": {
+      "diff": [
+        {
+          "path": "bin",
+          "status": "ADDED"
+        },
+        {
+          "path": "file1",
+          "status": "ADDED"
+        },
+        {
+          "path": "file3",
+          "status": "ADDED"
+        },
+        {
+          "path": "file4",
+          "status": "DELETED"
+        }
+      ]
+    }
+  } (no-eol)
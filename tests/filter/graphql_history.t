@@ -0,0 +1,112 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init repo 1> /dev/null
+  $ cd repo
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+
+  $ mkdir sub2
+  $ echo contents2 > sub2/file2
+  $ git add sub2
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ echo contents3 > sub1/file3
+  $ git add sub1
+  $ git commit -m "add file3" 1> /dev/null
+
+  $ cat > x.graphql <<EOF
+  > query {
+  >  rev(filter: ":/sub1") {
+  >   history(first: 1) {
+  >    edges {
+  >     cursor
+  >     node {
+  >      summary
+  >     }
+  >    }
+  >    pageInfo {
+  >     hasNextPage
+  >     endCursor
+  >    }
+  >   }
+  >   rest: history(after: "0b4cf6c9efbbda1eada39fa9c1d21d2525b027bb") {
+  >    nodes {
+  >     hash
+  >     summary
+  >    }
+  >    pageInfo {
+  >     hasNextPage
+  >    }
+  >   }
+  >   file3: history(path: "file3") {
+  >    nodes {
+  >     summary
+  >    }
+  >   }
+  >  }
+  > }
+  > EOF
+  $ git add x.graphql
+  $ git commit -m "add query" 1> /dev/null
+
+  $ josh-filter -q "graphql=x.graphql"
+  {
+    "rev": {
+      "history": {
+        "edges": [
+          {
+            "cursor": "0b4cf6c9efbbda1eada39fa9c1d21d2525b027bb",
+            "node": {
+              "summary": "add file3"
+            }
+          }
+        ],
+        "pageInfo": {
+          "hasNextPage": true,
+          "endCursor": "0b4cf6c9efbbda1eada39fa9c1d21d2525b027bb"
+        }
+      },
+      "rest": {
+        "nodes": [
+          {
+            "hash": "0b4cf6c9efbbda1eada39fa9c1d21d2525b027bb",
+            "summary": "add file1"
+          }
+        ],
+        "pageInfo": {
+          "hasNextPage": false
+        }
+      },
+      "file3": {
+        "nodes": [
+          {
+            "summary": "add file3"
+          }
+        ]
+      }
+    }
+  } (no-eol)
+
+Cursors that are not part of the history are rejected
+  $ cat > y.graphql <<EOF
+  > query {
+  >  rev(filter: ":/sub1") {
+  >   history(after: "bb282e9cdc1b972fffd08fd21eead43bc0c83cb8") {
+  >    nodes {
+  >     summary
+  >    }
+  >   }
+  >  }
+  > }
+  > EOF
+  $ git add y.graphql
+  $ git commit -m "add bad query" 1> /dev/null
+
+  $ josh-filter -q "graphql=y.graphql"
+  {
+    "rev": null
+  } (no-eol)
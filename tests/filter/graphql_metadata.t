@@ -0,0 +1,78 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init repo 1> /dev/null
+  $ cd repo
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ cat > ${TESTTMP}/msg <<EOF
+  > add file1
+  > 
+  > More details
+  > 
+  > Change-Id: I1234
+  > Signed-off-by: Josh <josh@example.com>
+  > EOF
+  $ GIT_AUTHOR_NAME=Author GIT_AUTHOR_EMAIL=author@example.com \
+  > GIT_AUTHOR_DATE="2005-04-07T22:13:13+02:00" git commit -F ${TESTTMP}/msg 1> /dev/null
+
+  $ cat > x.graphql <<EOF
+  > query {
+  >  rev(filter: ":/sub1") {
+  >   filtered
+  >   tree
+  >   message
+  >   author {
+  >    name
+  >    email
+  >    time
+  >    timezone
+  >   }
+  >   committer {
+  >    name
+  >    time(format: "%d.%m.%Y %H:%M")
+  >    timezone
+  >   }
+  >   trailers {
+  >    key
+  >    value
+  >   }
+  >   changeId
+  >  }
+  > }
+  > EOF
+  $ git add x.graphql
+  $ git commit -m "add query" 1> /dev/null
+
+  $ josh-filter -q "graphql=x.graphql"
+  {
+    "rev": {
+      "filtered": "d1b1e7b1e5dbb5346da0a19854735e47f460904f",
+      "tree": "3d77ff51363c9825cc2a221fc0ba5a883a1a2c72",
+      "message": "add file1\n\nMore details\n\nChange-Id: I1234\nSigned-off-by: Josh <josh@example.com>\n",
+      "author": {
+        "name": "Author",
+        "email": "author@example.com",
+        "time": "2005-04-07T22:13:13+02:00",
+        "timezone": "+0200"
+      },
+      "committer": {
+        "name": "Josh",
+        "time": "07.04.2005 22:13",
+        "timezone": "+0000"
+      },
+      "trailers": [
+        {
+          "key": "Change-Id",
+          "value": "I1234"
+        },
+        {
+          "key": "Signed-off-by",
+          "value": "Josh <josh@example.com>"
+        }
+      ],
+      "changeId": "I1234"
+    }
+  } (no-eol)
@@ -0,0 +1,54 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init repo 1> /dev/null
+  $ cd repo
+
+  $ mkdir sub1 sub2
+  $ echo contents1 > sub1/file1
+  $ git add .
+  $ git commit -m "add file1" 1> /dev/null
+
+The same change to sub1 is made on two branches, so both filter to the same commit
+
+  $ git checkout -q -b a
+  $ echo contents2 > sub1/file2
+  $ git add .
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ git checkout -q -b b master
+  $ echo contents2 > sub1/file2
+  $ echo contents3 > sub2/file3
+  $ git add .
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ josh-filter :/sub1 a --update refs/heads/filtered_a
+  $ josh-filter :/sub1 b --update refs/heads/filtered_b
+  $ git rev-parse filtered_a filtered_b | uniq | wc -l
+  1
+
+  $ git checkout -q master
+  $ git merge -q --no-ff a -m "merge a" 1> /dev/null
+  $ git merge -q --no-ff b -m "merge b" 1> /dev/null
+
+  $ cat > x.graphql <<EOF
+  > query {
+  >  rev(filter: ":/sub1") {
+  >   rev(original: true) {
+  >    hash
+  >   }
+  >  }
+  > }
+  > EOF
+  $ git add x.graphql
+  $ git commit -m "add query" 1> /dev/null
+
+Of the commits the filtered commit is known to originate from, the one closest to the
+requested commit is picked, no matter in which order they were filtered
+
+  $ josh-filter -q "graphql=x.graphql" | grep -c "$(git rev-parse HEAD~1)"
+  1
+
+  $ josh-filter :/sub1 master --update refs/heads/filtered_master
+  $ josh-filter -q "graphql=x.graphql" | grep -c "$(git rev-parse HEAD~1)"
+  1
@@ -0,0 +1,61 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init repo 1> /dev/null
+  $ cd repo
+
+  $ mkdir -p sub1/dir sub2
+  $ printf "fn main() {\n    todo!();\n}\n" > sub1/main.rs
+  $ printf "// TODO: remove\nfn helper() {}\n" > sub1/dir/helper.rs
+  $ echo "todo in another view" > sub2/notes.txt
+  $ git add .
+  $ git commit -m "initial" 1> /dev/null
+
+  $ cat > x.graphql <<EOF
+  > query {
+  >  rev(filter: ":/sub1") {
+  >   plain: search(pattern: "todo") {
+  >    path
+  >    line
+  >    text
+  >   }
+  >   regex: search(pattern: "(?i)^ *(// *)?todo", regex: true) {
+  >    path
+  >    line
+  >   }
+  >   limited: search(pattern: "fn ", maxResults: 1) {
+  >    path
+  >   }
+  >  }
+  > }
+  > EOF
+  $ git add x.graphql
+  $ git commit -m "add query" 1> /dev/null
+
+  $ josh-filter -q "graphql=x.graphql"
+  {
+    "rev": {
+      "plain": [
+        {
+          "path": "main.rs",
+          "line": 2,
+          "text": "    todo!();"
+        }
+      ],
+      "regex": [
+        {
+          "path": "dir/helper.rs",
+          "line": 1
+        },
+        {
+          "path": "main.rs",
+          "line": 2
+        }
+      ],
+      "limited": [
+        {
+          "path": "dir/helper.rs"
+        }
+      ]
+    }
+  } (no-eol)
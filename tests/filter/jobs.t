@@ -0,0 +1,69 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1 sub2 sub3
+  $ echo contents1 > sub1/file1
+  $ git add .
+  $ git commit -m "add file1" 1> /dev/null
+  $ echo contents2 > sub2/file2
+  $ git add .
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ git checkout -b branch 2> /dev/null
+  $ echo contents3 > sub3/file3
+  $ echo contents4 > sub1/file4
+  $ git add .
+  $ git commit -m "add file3 and file4" 1> /dev/null
+
+  $ git checkout master 2> /dev/null
+  $ echo contents5 > sub2/file5
+  $ git add .
+  $ git commit -m "add file5" 1> /dev/null
+  $ git merge -q branch --no-ff -m "merge branch" 1> /dev/null
+  $ echo contents6 > sub3/file6
+  $ git add .
+  $ git commit -m "add file6" 1> /dev/null
+
+  $ cd ${TESTTMP}
+  $ git clone -q real_repo serial_repo
+
+Filtering the members of a composed filter on several threads gives the same result
+as doing it on one thread
+
+  $ cd ${TESTTMP}/real_repo
+  $ josh-filter -j 4 ":[a=:/sub1,b=:/sub2,c=:/sub3]" master --update refs/heads/filtered
+  $ git ls-tree -r --name-only filtered
+  a/file1
+  a/file4
+  b/file2
+  b/file5
+  c/file3
+  c/file6
+
+  $ cd ${TESTTMP}/serial_repo
+  $ josh-filter -j 1 ":[a=:/sub1,b=:/sub2,c=:/sub3]" origin/master --update refs/heads/filtered
+  $ test "$(git rev-parse filtered)" = "$(git -C ${TESTTMP}/real_repo rev-parse filtered)"
+
+The same holds when the filters of all threads are already cached
+
+  $ cd ${TESTTMP}/real_repo
+  $ josh-filter -j 4 ":[a=:/sub1,b=:/sub2,c=:/sub3]" master --update refs/heads/filtered_again
+  $ git rev-parse filtered filtered_again | uniq | wc -l
+  1
+
+Nested composed filters share the same limit of threads
+
+  $ cd ${TESTTMP}/real_repo
+  $ josh-filter -j 2 ":[a=:[x=:/sub1,y=:/sub2],b=:[z=:/sub3,w=:/sub1]]" master --update refs/heads/nested
+  $ cd ${TESTTMP}/serial_repo
+  $ josh-filter ":[a=:[x=:/sub1,y=:/sub2],b=:[z=:/sub3,w=:/sub1]]" origin/master --update refs/heads/nested
+  $ test "$(git rev-parse nested)" = "$(git -C ${TESTTMP}/real_repo rev-parse nested)"
+
+--pack can't be used with more than one thread
+
+  $ josh-filter -j 2 --pack :/sub1 origin/master
+  ERROR: JoshError("--pack can't be combined with -j")
+  [1]
@@ -0,0 +1,53 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+
+  $ mkdir sub2
+  $ echo contents2 > sub2/file2
+  $ git add sub2
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ echo contents3 > sub1/file3
+  $ git add sub1
+  $ git commit -m "add file3" 1> /dev/null
+
+  $ git notes --ref ci add -m "build 1 ok" HEAD~2
+  $ git notes --ref ci add -m "build 2 ok" HEAD~1
+  $ git notes --ref ci add -m "build 3 ok" HEAD
+
+  $ josh-filter :/sub1 master --update refs/heads/sub1
+  $ josh-filter :/sub1 refs/notes/ci --update refs/notes/ci_sub1
+
+The note of "add file2" is gone, as that commit is not part of the filtered history
+  $ git notes --ref ci_sub1 list | wc -l
+  2
+  $ git log --pretty=%s sub1 --
+  add file3
+  add file1
+  $ git notes --ref ci_sub1 show sub1
+  build 3 ok
+  $ git notes --ref ci_sub1 show sub1~1
+  build 1 ok
+
+The filtered notes history follows the original one, notes commits that only change
+dropped notes are skipped
+  $ git log --pretty=%s refs/notes/ci_sub1
+  Notes added by 'git notes add'
+  Notes added by 'git notes add'
+
+Filtering again after a notes change builds on top of the previous filtered notes commit
+  $ git rev-parse refs/notes/ci_sub1 > ${TESTTMP}/before
+  $ git notes --ref ci append -m "deployed" HEAD
+  $ josh-filter :/sub1 refs/notes/ci --update refs/notes/ci_sub1
+  $ git rev-parse refs/notes/ci_sub1~1 | diff - ${TESTTMP}/before
+  $ git notes --ref ci_sub1 show sub1
+  build 3 ok
+  
+  deployed
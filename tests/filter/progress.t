@@ -0,0 +1,27 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1 sub2
+  $ echo contents1 > sub1/file1
+  $ git add .
+  $ git commit -m "add file1" 1> /dev/null
+  $ echo contents2 > sub2/file2
+  $ git add .
+  $ git commit -m "add file2" 1> /dev/null
+  $ echo contents3 > sub1/file3
+  $ git add .
+  $ git commit -m "add file3" 1> /dev/null
+
+  $ josh-filter --progress :/sub1 master 2>&1 | tr '\r' '\n'
+  
+  [##############################] 3/3 :/sub1
+
+Nothing is reported once the history is filtered
+
+  $ josh-filter --progress :/sub1 master
+  $ git log --pretty=%s FILTERED_HEAD
+  add file3
+  add file1
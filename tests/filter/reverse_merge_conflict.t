@@ -0,0 +1,56 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1 sub2
+  $ echo contents1 > sub1/file1
+  $ echo contents1 > sub2/file2
+  $ git add .
+  $ git commit -m "initial" 1> /dev/null
+  $ git branch branch1
+
+Change the same file outside of the filter on two branches
+
+  $ echo contents2 > sub2/file2
+  $ echo contents3 > sub1/file3
+  $ git add .
+  $ git commit -m "change sub2 on master" 1> /dev/null
+
+  $ git checkout -q branch1
+  $ echo contents3 > sub2/file2
+  $ echo contents4 > sub1/file4
+  $ git add .
+  $ git commit -m "change sub2 on branch1" 1> /dev/null
+
+  $ git checkout -q master
+  $ git merge -q -s ours branch1 -m "merge branch1" 1> /dev/null
+
+  $ josh-filter :exclude[:/sub2] master --update refs/heads/hidden_master
+
+Merge the two branches again in the filtered view. The merge is clean inside of the
+view, but the original parents conflict outside of it
+
+  $ git checkout -q -b hidden_work hidden_master^1
+  $ echo contents5 > sub1/file5
+  $ git add sub1/file5
+  $ git commit -m "add file5" 1> /dev/null
+  $ git merge -q --no-ff hidden_master^2 -m "merge branch1 again"
+  $ git ls-tree -r --name-only HEAD
+  sub1/file1
+  sub1/file3
+  sub1/file4
+  sub1/file5
+
+  $ git checkout -q master
+  $ git branch -f hidden_master hidden_work
+  $ josh-filter :exclude[:/sub2] --reverse master --update refs/heads/hidden_master
+  conflicting paths:
+  sub2/file2
+  [1]
+
+The upstream branch is left unchanged
+
+  $ git log --pretty=%s -1 master
+  merge branch1
@@ -0,0 +1,56 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ mkdir sub2
+  $ echo contents1 > sub2/file2
+  $ git add sub1 sub2
+
+Signatures are not verified, so a fake one will do
+  $ cat > signed_commit <<EOF
+  > tree $(git write-tree)
+  > author Josh <josh@example.com> 1112911993 +0000
+  > committer Josh <josh@example.com> 1112911993 +0000
+  > gpgsig -----BEGIN PGP SIGNATURE-----
+  >  not a real signature
+  >  -----END PGP SIGNATURE-----
+  > 
+  > signed commit
+  > EOF
+  $ git update-ref refs/heads/master $(git hash-object -t commit -w signed_commit)
+
+  $ josh-filter :/sub1 master
+  Invalidated signatures: 1
+  $ git cat-file commit FILTERED_HEAD | grep -c gpgsig
+  0
+  [1]
+
+  $ josh-filter :/sub2 master --signatures resign
+  ERROR: JoshError("--signatures=resign needs --signing-key")
+  [1]
+
+A filter that does not change the commit keeps the signature, unless it is stripped.
+Rewritten members of the composition are counted, cached results are not
+  $ josh-filter ":[sub1=:/sub1,sub2=:/sub2]" master --update refs/heads/preserved
+  Invalidated signatures: 1
+  $ git rev-parse master preserved
+  43291f17dc4e95cc2382b33d72655c4c8d367761
+  43291f17dc4e95cc2382b33d72655c4c8d367761
+  $ josh-filter ":[sub1=:/sub1,sub2=:/sub2]" master --update refs/heads/stripped --signatures strip
+  Invalidated signatures: 3
+  $ git cat-file commit stripped | grep -c gpgsig
+  0
+  [1]
+  $ git diff preserved stripped --stat
+
+  $ ssh-keygen -q -t ed25519 -N "" -C josh -f ${TESTTMP}/key
+  $ echo "josh@example.com $(cat ${TESTTMP}/key.pub)" > ${TESTTMP}/allowed_signers
+  $ josh-filter :/sub1 master --update refs/heads/resigned --signatures resign --signing-key ${TESTTMP}/key
+  $ git cat-file commit resigned | grep -c "BEGIN SSH SIGNATURE"
+  1
+  $ git -c gpg.ssh.allowedSignersFile=${TESTTMP}/allowed_signers verify-commit resigned 2>&1
+  Good "git" signature for josh@example.com with ED25519 key SHA256:* (glob)
@@ -0,0 +1,46 @@
+  $ export TESTTMP=${PWD}
+
+  $ cd ${TESTTMP}
+  $ git init real_repo 1> /dev/null
+  $ cd real_repo
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+
+  $ echo contents2 > sub1/file2
+  $ git add sub1
+  $ git commit -m "add file2" 1> /dev/null
+
+  $ git checkout -q -b branch1
+  $ echo contents3 > sub1/file3
+  $ git add sub1
+  $ git commit -m "add file3" 1> /dev/null
+
+  $ git checkout -q master
+  $ echo contents4 > sub1/file4
+  $ git add sub1
+  $ git commit -m "add file4" 1> /dev/null
+  $ git merge -q branch1 --no-ff
+
+  $ josh-filter ":since=$(git rev-parse HEAD~2):/sub1" master --update refs/heads/recent
+  $ git log --graph --pretty=%s recent
+  *   Merge branch 'branch1'
+  |\  
+  | * add file3
+  * | add file4
+  |/  
+  * add file2
+  $ git ls-tree --name-only recent
+  file1
+  file2
+  file3
+  file4
+
+Commits that don't descend from the cutoff are dropped
+
+  $ josh-filter ":since=$(git rev-parse HEAD^2):/sub1" master --update refs/heads/recent2
+  $ git log --graph --pretty=%s recent2
+  * Merge branch 'branch1'
+  * add file3
@@ -117,6 +117,74 @@
   
   1 directory, 4 files
 
+Amend again, this time asking josh to merge with the change upstream
+
+  $ cd ${TESTTMP}/remote/real_repo.git/
+  $ git update-ref -d refs/for/master
+
+  $ cd ${TESTTMP}/sub
+  $ echo content5 > file_new2 1> /dev/null
+  $ git add .
+  $ git commit --amend --no-edit -q
+  $ git push -o amend origin HEAD:refs/for/master 2>&1 >/dev/null | sed -e 's/[ ]*$//g'
+  remote: josh-proxy
+  remote: response from upstream:
+  remote: To http://localhost:8001/real_repo.git
+  remote:  * [new reference]   JOSH_PUSH -> refs/for/master
+  remote:
+  remote:
+  To http://localhost:8002/real_repo.git:/sub3.git
+   * [new reference]   HEAD -> refs/for/master
+
+  $ cd ${TESTTMP}/real_repo
+  $ git fetch -q http://localhost:8002/real_repo.git@refs/for/master:nop.git && git checkout -q FETCH_HEAD
+  $ git log --graph --pretty=%s
+  * Add in full
+  * add file3
+  * initial
+  $ tree
+  .
+  |-- file1
+  |-- file_outside
+  `-- sub3
+      |-- file2x
+      |-- file3
+      |-- file_new
+      `-- file_new2
+  
+  1 directory, 6 files
+
+Upload a second patchset that modifies a file which then gets removed on master
+
+  $ cd ${TESTTMP}/remote/real_repo.git/
+  $ git update-ref -d refs/for/master
+
+  $ cd ${TESTTMP}/full
+  $ echo content_changed > file1
+  $ git commit -aq --amend --no-edit
+  $ git push -q http://localhost:8001/real_repo.git HEAD:refs/changes/1/2
+
+  $ cd ${TESTTMP}/real_repo
+  $ git checkout -q master
+  $ git rm -q file1
+  $ git commit -q -m "remove file1"
+  $ git push -q
+
+  $ cd ${TESTTMP}/sub
+  $ echo content6 > file_new3 1> /dev/null
+  $ git add .
+  $ git commit --amend --no-edit -q
+  $ git push -o amend origin HEAD:refs/for/master 2>&1 >/dev/null | sed -e 's/[ ]*$//g'
+  remote: josh-proxy
+  remote: response from upstream:
+  remote: rejecting to amend "Add in full" with conflicting changes
+  remote:
+  remote:
+  remote: error: hook declined to update refs/for/master
+  To http://localhost:8002/real_repo.git:/sub3.git
+   ! [remote rejected] HEAD -> refs/for/master (hook declined)
+  error: failed to push some refs to 'http://localhost:8002/real_repo.git:/sub3.git'
+
   $ bash ${TESTDIR}/destroy_test_env.sh
   "real_repo.git" = [':/sub3']
   refs
@@ -135,7 +203,8 @@
   |           `-- refs
   |               |-- changes
   |               |   `-- 1
-  |               |       `-- 1
+  |               |       |-- 1
+  |               |       `-- 2
   |               |-- for
   |               |   `-- master
   |               `-- heads
@@ -143,4 +212,4 @@
   |-- namespaces
   `-- tags
   
-  17 directories, 5 files
+  17 directories, 6 files
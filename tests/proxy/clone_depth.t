@@ -0,0 +1,78 @@
+  $ . ${TESTDIR}/setup_test_env.sh
+  $ cd ${TESTTMP}
+
+  $ git clone -q http://localhost:8001/real_repo.git 1> /dev/null
+  warning: You appear to have cloned an empty repository.
+  $ cd ${TESTTMP}/real_repo
+
+  $ mkdir sub1
+  $ for i in 1 2 3 4; do echo $i > sub1/file; git add sub1; git commit -q -m "commit $i"; done
+  $ git push -q
+
+  $ cd ${TESTTMP}
+  $ git clone -q http://localhost:8002/real_repo.git:/sub1:depth=2.git sub1
+  $ cd ${TESTTMP}/sub1
+  $ git log --pretty=%s
+  commit 4
+  commit 3
+  $ git rev-parse HEAD > ${TESTTMP}/before
+
+The view keeps its start commit while the branch grows, so fetching is a fast-forward
+
+  $ cd ${TESTTMP}/real_repo
+  $ echo 5 > sub1/file
+  $ git commit -q -a -m "commit 5"
+  $ git push -q
+
+  $ cd ${TESTTMP}/sub1
+  $ git fetch -q
+  $ git log --pretty=%s origin/master
+  commit 5
+  commit 4
+  commit 3
+  $ git merge-base --is-ancestor $(cat ${TESTTMP}/before) origin/master
+
+After another "depth" commits the view moves on to a new start commit
+
+  $ cd ${TESTTMP}/real_repo
+  $ echo 6 > sub1/file
+  $ git commit -q -a -m "commit 6"
+  $ git push -q
+
+  $ cd ${TESTTMP}/sub1
+  $ git fetch -q
+  $ git log --pretty=%s origin/master
+  commit 6
+  commit 5
+
+Invalid depths are rejected
+
+  $ git ls-remote http://localhost:8002/real_repo.git:/sub1:depth=0.git 2>&1 | head -1
+  fatal: unable to access 'http://localhost:8002/real_repo.git:/sub1:depth=0.git/': The requested URL returned error: 422
+
+  $ bash ${TESTDIR}/destroy_test_env.sh
+  "real_repo.git" = [':/sub1']
+  refs
+  |-- heads
+  |-- josh
+  |   |-- depth
+  |   |   `-- real_repo.git
+  |   |       `-- fbe0a1c58ea6f51a2be62f0d2217c6d459a01753
+  |   |           `-- 2
+  |   |               `-- refs
+  |   |                   `-- heads
+  |   |                       `-- master
+  |   |-- filtered
+  |   |   `-- real_repo.git
+  |   |       `-- %3A%2Fsub1
+  |   |           `-- heads
+  |   |               `-- master
+  |   `-- upstream
+  |       `-- real_repo.git
+  |           `-- refs
+  |               `-- heads
+  |                   `-- master
+  |-- namespaces
+  `-- tags
+  
+  18 directories, 3 files
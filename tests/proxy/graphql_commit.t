@@ -0,0 +1,83 @@
+  $ . ${TESTDIR}/setup_test_env.sh
+  $ cd ${TESTTMP}
+
+  $ git clone -q http://localhost:8001/real_repo.git 1> /dev/null
+  warning: You appear to have cloned an empty repository.
+  $ cd ${TESTTMP}/real_repo
+
+  $ mkdir sub1 sub2
+  $ echo contents1 > sub1/file1
+  $ echo contents2 > sub2/file2
+  $ git add .
+  $ git commit -m "add files" 1> /dev/null
+  $ git push -q
+
+  $ cd ${TESTTMP}
+  $ git clone -q http://localhost:8002/real_repo.git:/sub1.git sub1
+
+Only branches can be committed to
+
+  $ cat > ${TESTTMP}/query <<EOF
+  > {"query":"mutation {
+  >   commit(rev: \"master';touch pwned;'\", filter: \":/sub1\", message: \"change file1\",
+  >     author: { name: \"Someone\", email: \"someone@example.com\" },
+  >     changes: [{ path: \"file1\", content: \"changed\" }])
+  > }"}
+  > EOF
+  $ curl -s -X POST -H "content-type: application/json" --data @${TESTTMP}/query "http://localhost:8002/~/graphql/real_repo.git" | grep -c "must be a branch like refs/heads/<branchname>"
+  1
+  $ find ${TESTTMP} -name pwned
+
+The commit is made in the filtered view and mapped back to upstream like a push
+
+  $ cat > ${TESTTMP}/query <<EOF
+  > {"query":"mutation {
+  >   commit(rev: \"refs/heads/master\", filter: \":/sub1\", message: \"change file1\",
+  >     author: { name: \"Someone\", email: \"someone@example.com\" },
+  >     changes: [{ path: \"file1\", content: \"changed\" }])
+  > }"}
+  > EOF
+  $ curl -s -X POST -H "content-type: application/json" --data @${TESTTMP}/query "http://localhost:8002/~/graphql/real_repo.git" > ${TESTTMP}/response
+  $ cat ${TESTTMP}/response
+  {
+    "data": {
+      "commit": "*" (glob)
+    }
+  } (no-eol)
+
+  $ cd ${TESTTMP}/real_repo
+  $ git pull -q
+  $ test "$(grep -o "[0-9a-f]\{40\}" ${TESTTMP}/response)" = "$(git rev-parse HEAD)"
+  $ git log --pretty="%an <%ae>, %cn <%ce>: %s"
+  Someone <someone@example.com>, Someone <someone@example.com>: change file1
+  Josh <josh@example.com>, Josh <josh@example.com>: add files
+  $ cat sub1/file1
+  changed (no-eol)
+  $ cat sub2/file2
+  contents2
+
+  $ bash ${TESTDIR}/destroy_test_env.sh
+  "real_repo.git" = [
+      ':/sub1',
+      ':/sub2',
+  ]
+  refs
+  |-- heads
+  |-- josh
+  |   |-- filtered
+  |   |   `-- real_repo.git
+  |   |       |-- %3A%2Fsub1
+  |   |       |   `-- heads
+  |   |       |       `-- master
+  |   |       `-- %3A%2Fsub2
+  |   |           `-- heads
+  |   |               `-- master
+  |   `-- upstream
+  |       `-- real_repo.git
+  |           `-- refs
+  |               `-- heads
+  |                   `-- master
+  |-- namespaces
+  `-- tags
+  
+  14 directories, 3 files
@@ -0,0 +1,120 @@
+  $ . ${TESTDIR}/setup_test_env.sh
+  $ cd ${TESTTMP}
+
+  $ git clone -q http://localhost:8001/real_repo.git
+  warning: You appear to have cloned an empty repository.
+
+  $ cd real_repo
+
+  $ mkdir -p a/b
+  $ echo abdcontent > a/b/d
+
+  $ mkdir sub1
+  $ echo contents > sub1/file1
+  $ git add .
+  $ git commit -m "add file1" 1> /dev/null
+
+  $ git tag -a v1 -m "release 1"
+  $ git tag v1-light
+
+  $ git push -q
+  $ git push -q --tags
+
+  $ cd ${TESTTMP}
+
+  $ git clone -q http://localhost:8002/real_repo.git full_repo
+
+  $ cat > query <<EOF
+  > {"query":"{
+  >  branches: refs {
+  >   name, kind, tag { name }
+  >  }
+  >  tags: refs(pattern: \"refs/tags/*\") {
+  >   name, kind
+  >   tag {
+  >    name, message, target
+  >    tagger { name, email }
+  >   }
+  >   rev { hash }
+  >  }
+  >  rev(at: \"refs/tags/v1\") { hash }
+  > }"}
+  > EOF
+
+  $ cat query | curl -s -X POST -H "content-type: application/json" --data @- "http://localhost:8002/~/graphql/real_repo.git"
+  {
+    "data": {
+      "branches": [
+        {
+          "name": "refs/heads/master",
+          "kind": "BRANCH",
+          "tag": null
+        }
+      ],
+      "tags": [
+        {
+          "name": "refs/tags/v1",
+          "kind": "TAG",
+          "tag": {
+            "name": "v1",
+            "message": "release 1\n",
+            "target": "1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4",
+            "tagger": {
+              "name": "Josh",
+              "email": "josh@example.com"
+            }
+          },
+          "rev": {
+            "hash": "1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4"
+          }
+        },
+        {
+          "name": "refs/tags/v1-light",
+          "kind": "TAG",
+          "tag": null,
+          "rev": {
+            "hash": "1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4"
+          }
+        }
+      ],
+      "rev": {
+        "hash": "1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4"
+      }
+    }
+  } (no-eol)
+
+  $ bash ${TESTDIR}/destroy_test_env.sh
+  "real_repo.git" = [
+      ':/a',
+      ':/a/b',
+      ':/sub1',
+  ]
+  refs
+  |-- heads
+  |-- josh
+  |   |-- filtered
+  |   |   `-- real_repo.git
+  |   |       |-- %3A
+  |   |       |   `-- heads
+  |   |       |       `-- master
+  |   |       |-- %3A%2Fa
+  |   |       |   `-- heads
+  |   |       |       `-- master
+  |   |       |-- %3A%2Fa%2Fb
+  |   |       |   `-- heads
+  |   |       |       `-- master
+  |   |       `-- %3A%2Fsub1
+  |   |           `-- heads
+  |   |               `-- master
+  |   `-- upstream
+  |       `-- real_repo.git
+  |           `-- refs
+  |               |-- heads
+  |               |   `-- master
+  |               `-- tags
+  |                   |-- v1
+  |                   `-- v1-light
+  |-- namespaces
+  `-- tags
+  
+  19 directories, 7 files
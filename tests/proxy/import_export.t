@@ -39,7 +39,6 @@
 $ curl -s http://localhost:8002/flush
 Flushed credential cache
   $ git fetch --force http://localhost:8002/repo1.git:prefix=repo1.git master:repo1_in_subdir 1> /dev/null
-  warning: no common commits
   From http://localhost:8002/repo1.git:prefix=repo1
    * [new branch]      master     -> repo1_in_subdir
   $ git checkout repo1_in_subdir
@@ -320,6 +320,209 @@
   } (no-eol)
 
 
+  $ cat > ../query <<EOF
+  > {"query":"mutation {
+  >   metaReplace(commit: \"1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4\", topic:\"tool/warn\", replace: [
+  >      { path:\"a/b/d\", id: \"c6058f73704cfe1879d4ef110910fc8b50ff04c7\",
+  >        data: \"{\\\\\"L\\\\\":3,\\\\\"location\\\\\":\\\\\"1235\\\\\",\\\\\"message\\\\\":\\\\\"fixed\\\\\"}\" }
+  >   ])
+  >   metaRemove(commit: \"1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4\", topic:\"tool/warn\", remove: [
+  >      { path:\"a/b/c\", ids: [\"43a0f340d27ea912af7a1cfbaa491cd117564a4e\"] }
+  >   ])
+  > }"}
+  > EOF
+
+  $ cat ../query | curl -s -X POST -H "content-type: application/json" --data @- "http://localhost:8002/~/graphql/real_repo.git"
+  {
+    "data": {
+      "metaReplace": true,
+      "metaRemove": true
+    }
+  } (no-eol)
+
+  $ git fetch -q ${TESTTMP}/remote/scratch refs/josh/upstream/real_repo.git/refs/josh/meta
+
+  $ git show FETCH_HEAD:tool/warn/~/1e/64d/c713/1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4/a/b/c
+  c6058f73704cfe1879d4ef110910fc8b50ff04c7:{"location":"1235","message":"foobar"} (no-eol)
+
+  $ git show FETCH_HEAD:tool/warn/~/1e/64d/c713/1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4/a/b/d
+  53296c9e4dbc2b6ad15e15b2fc66870cd0548515:{"location":"1236","message":"foobar"}
+  a8dd084ef69afb8e883c68224a8d15b7ba0990f8:{"L":3,"location":"1235","message":"fixed"} (no-eol)
+
+  $ cat > ../query <<EOF
+  > {"query":"{ rev(at:\"refs/heads/master\") {
+  >  all: markers(topic:\"tool/warn\") {
+  >   path, data { id, message: string(at: \"/message\") }
+  >  }
+  >  lines: markers(topic:\"tool/warn\", conditions: [{ at: \"/L\", min: 1, max: 5 }]) {
+  >   path, data { id, line: int(at: \"/L\") }
+  >  }
+  >  file(path:\"a/b/d\") {
+  >   meta(topic:\"tool/warn\") {
+  >    data(conditions: [{ at: \"/message\", equals: \"foobar\" }]) { id }
+  >   }
+  >  }
+  > }}"}
+  > EOF
+
+  $ cat ../query | curl -s -X POST -H "content-type: application/json" --data @- "http://localhost:8002/~/graphql/real_repo.git"
+  {
+    "data": {
+      "rev": {
+        "all": [
+          {
+            "path": "a/b/c",
+            "data": {
+              "id": "c6058f73704cfe1879d4ef110910fc8b50ff04c7",
+              "message": "foobar"
+            }
+          },
+          {
+            "path": "a/b/d",
+            "data": {
+              "id": "53296c9e4dbc2b6ad15e15b2fc66870cd0548515",
+              "message": "foobar"
+            }
+          },
+          {
+            "path": "a/b/d",
+            "data": {
+              "id": "a8dd084ef69afb8e883c68224a8d15b7ba0990f8",
+              "message": "fixed"
+            }
+          }
+        ],
+        "lines": [
+          {
+            "path": "a/b/d",
+            "data": {
+              "id": "a8dd084ef69afb8e883c68224a8d15b7ba0990f8",
+              "line": 3
+            }
+          }
+        ],
+        "file": {
+          "meta": {
+            "data": [
+              {
+                "id": "53296c9e4dbc2b6ad15e15b2fc66870cd0548515"
+              }
+            ]
+          }
+        }
+      }
+    }
+  } (no-eol)
+
+
+  $ cat > ../query <<EOF
+  > {"query":"mutation {
+  >   meta(commit: \"1e64dc7136eae9c6b88e4ab831322f3c72a5c0e4\", topic:\"tool/warn\", add: [
+  >      { path:\"sub1/file1\", data: [\"{\\\\\"L\\\\\":1,\\\\\"message\\\\\":\\\\\"review\\\\\"}\"] }
+  >   ])
+  > }"}
+  > EOF
+
+  $ cat ../query | curl -s -X POST -H "content-type: application/json" --data @- "http://localhost:8002/~/graphql/real_repo.git"
+  {
+    "data": {
+      "meta": true
+    }
+  } (no-eol)
+
+  $ cd ${TESTTMP}/real_repo
+  $ printf "new\nabdcontent\n" > a/b/d
+  $ echo changed > sub1/file1
+  $ git commit -q -am "change files"
+  $ git push -q
+  $ cd ${TESTTMP}/full_repo
+  $ git fetch -q origin
+
+  $ cat > ../query <<EOF
+  > {"query":"{ rev(at:\"refs/heads/master\") {
+  >  files {
+  >   path, meta(topic:\"tool/warn\") {
+  >     own: data { id }
+  >     carried: data(ancestors: 1) {
+  >       id
+  >       line: int(at: \"/L\")
+  >       outdated: bool(at: \"/outdated\")
+  >     }
+  >     count(ancestors: 1)
+  >   }
+  >  }
+  >  dirs {
+  >   path, meta(topic:\"tool/warn\") {
+  >     count(ancestors: 1)
+  >   }
+  >  }
+  > }}"}
+  > EOF
+
+  $ cat ../query | curl -s -X POST -H "content-type: application/json" --data @- "http://localhost:8002/~/graphql/real_repo.git"
+  {
+    "data": {
+      "rev": {
+        "files": [
+          {
+            "path": "a/b/d",
+            "meta": {
+              "own": [],
+              "carried": [
+                {
+                  "id": "53296c9e4dbc2b6ad15e15b2fc66870cd0548515",
+                  "line": null,
+                  "outdated": null
+                },
+                {
+                  "id": "a8dd084ef69afb8e883c68224a8d15b7ba0990f8",
+                  "line": 4,
+                  "outdated": null
+                }
+              ],
+              "count": 2
+            }
+          },
+          {
+            "path": "sub1/file1",
+            "meta": {
+              "own": [],
+              "carried": [
+                {
+                  "id": "7c604734a362692b2523709cc4ca2a624d9debe7",
+                  "line": 1,
+                  "outdated": true
+                }
+              ],
+              "count": 1
+            }
+          }
+        ],
+        "dirs": [
+          {
+            "path": "a",
+            "meta": {
+              "count": 2
+            }
+          },
+          {
+            "path": "a/b",
+            "meta": {
+              "count": 2
+            }
+          },
+          {
+            "path": "sub1",
+            "meta": {
+              "count": 1
+            }
+          }
+        ]
+      }
+    }
+  } (no-eol)
+
+
   $ bash ${TESTDIR}/destroy_test_env.sh
   "real_repo.git" = [
       ':/a',
@@ -0,0 +1,110 @@
+  $ . ${TESTDIR}/setup_test_env.sh
+  $ cd ${TESTTMP}
+
+  $ git clone -q http://localhost:8001/real_repo.git 1> /dev/null
+  warning: You appear to have cloned an empty repository.
+  $ cd ${TESTTMP}/real_repo
+
+  $ mkdir sub1 sub2
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+  $ echo contents2 > sub2/file2
+  $ git add sub2
+  $ git commit -m "add file2" 1> /dev/null
+  $ echo contents3 > sub1/file3
+  $ git add sub1
+  $ git commit -m "add file3" 1> /dev/null
+  $ git notes --ref ci add -m "build 1 ok" HEAD~2
+  $ git notes --ref ci add -m "build 2 ok" HEAD~1
+  $ git push -q origin master refs/notes/ci
+
+  $ cd ${TESTTMP}
+  $ git clone -q http://localhost:8002/real_repo.git:/sub1.git sub1
+  $ cd ${TESTTMP}/sub1
+  $ git fetch -q origin "refs/notes/*:refs/notes/*"
+  $ git log --pretty=%s
+  add file3
+  add file1
+  $ git notes --ref ci list | wc -l
+  1
+  $ git notes --ref ci show HEAD~1
+  build 1 ok
+
+Notes pushed to the view get attached to the original commits
+
+  $ git notes --ref ci add -m "build 3 ok" HEAD
+  $ git push origin refs/notes/ci 2>&1 >/dev/null | sed -e 's/[ ]*$//g'
+  remote: josh-proxy
+  remote: response from upstream:
+  remote: To http://localhost:8001/real_repo.git
+  remote:    *  JOSH_PUSH -> refs/notes/ci (glob)
+  remote:
+  remote:
+  To http://localhost:8002/real_repo.git:/sub1.git
+     *  refs/notes/ci -> refs/notes/ci (glob)
+
+  $ cd ${TESTTMP}/real_repo
+  $ git fetch -q origin +refs/notes/ci:refs/notes/ci
+  $ git notes --ref ci list | wc -l
+  3
+  $ git notes --ref ci show master
+  build 3 ok
+  $ git notes --ref ci show master~1
+  build 2 ok
+  $ git notes --ref ci show master~2
+  build 1 ok
+
+Removing a note in the view removes it upstream, notes outside of the view stay
+
+  $ cd ${TESTTMP}/sub1
+  $ git notes --ref ci remove HEAD~1
+  Removing note for object * (glob)
+  $ git push -q origin refs/notes/ci 2>/dev/null
+
+  $ cd ${TESTTMP}/real_repo
+  $ git fetch -q origin +refs/notes/ci:refs/notes/ci
+  $ git notes --ref ci list | wc -l
+  2
+  $ git notes --ref ci show master~1
+  build 2 ok
+
+Pushing notes that don't change anything does not create the notes ref upstream
+
+  $ cd ${TESTTMP}/sub1
+  $ git notes --ref empty add -m "temporary" HEAD
+  $ git notes --ref empty remove HEAD
+  Removing note for object * (glob)
+  $ git push -q origin refs/notes/empty 2>/dev/null
+
+  $ cd ${TESTTMP}/real_repo
+  $ git ls-remote origin refs/notes/*
+  *	refs/notes/ci (glob)
+
+  $ bash ${TESTDIR}/destroy_test_env.sh
+  "real_repo.git" = [
+      ':/sub1',
+      ':/sub2',
+  ]
+  refs
+  |-- heads
+  |-- josh
+  |   |-- filtered
+  |   |   `-- real_repo.git
+  |   |       |-- %3A%2Fsub1
+  |   |       |   `-- heads
+  |   |       |       `-- master
+  |   |       `-- %3A%2Fsub2
+  |   |           `-- heads
+  |   |               `-- master
+  |   `-- upstream
+  |       `-- real_repo.git
+  |           `-- refs
+  |               |-- heads
+  |               |   `-- master
+  |               `-- notes
+  |                   `-- ci
+  |-- namespaces
+  `-- tags
+  
+  15 directories, 4 files
@@ -0,0 +1,122 @@
+  $ . ${TESTDIR}/setup_test_env.sh
+  $ cd ${TESTTMP}
+
+  $ git clone -q http://localhost:8001/real_repo.git 1> /dev/null
+  warning: You appear to have cloned an empty repository.
+  $ cd ${TESTTMP}/real_repo
+
+  $ mkdir sub1 sub2
+  $ echo contents1 > sub1/file1
+  $ echo contents1 > sub2/file2
+  $ git add .
+  $ git commit -m "add file1" 1> /dev/null
+  $ git push -q
+
+  $ cd ${TESTTMP}
+  $ git clone -q http://localhost:8002/real_repo.git:/sub1.git sub1
+
+The upstream branch moves, inside and outside of the view
+
+  $ cd ${TESTTMP}/real_repo
+  $ echo contents3 > sub1/file3
+  $ echo contents2 > sub2/file2
+  $ git add .
+  $ git commit -m "upstream change" 1> /dev/null
+  $ git push -q
+
+  $ cd ${TESTTMP}/sub1
+  $ echo contents2 > file2
+  $ git add file2
+  $ git commit -m "add file2" 1> /dev/null
+
+The base has to be a full reference name
+
+  $ git push -o rebase -o "base=master';touch pwned;'" origin master:refs/for/master 2>&1 >/dev/null | sed -e 's/[ ]*$//g'
+  remote: josh-proxy
+  remote: response from upstream:
+  remote: invalid base "master';touch pwned;'": must be a full reference name like refs/heads/<branchname>
+  remote:
+  remote:
+  remote: error: hook declined to update refs/for/master
+  To http://localhost:8002/real_repo.git:/sub1.git
+   ! [remote rejected] master -> refs/for/master (hook declined)
+  error: failed to push some refs to 'http://localhost:8002/real_repo.git:/sub1.git'
+  $ find ${TESTTMP} -name pwned
+
+The pushed commit is replayed on top of the current upstream tip
+
+  $ git push -o rebase origin master:refs/for/master 2>&1 >/dev/null | sed -e 's/[ ]*$//g'
+  remote: josh-proxy
+  remote: response from upstream:
+  remote: To http://localhost:8001/real_repo.git
+  remote:  * [new reference]   JOSH_PUSH -> refs/for/master
+  remote: REWRITE(* -> *) (glob)
+  remote:
+  remote:
+  To http://localhost:8002/real_repo.git:/sub1.git
+   * [new reference]   master -> refs/for/master
+
+  $ cd ${TESTTMP}/real_repo
+  $ git fetch -q origin refs/for/master:rfm
+  $ git log --pretty=%s rfm
+  add file2
+  upstream change
+  add file1
+  $ git ls-tree -r --name-only rfm
+  sub1/file1
+  sub1/file2
+  sub1/file3
+  sub2/file2
+  $ git show rfm:sub2/file2
+  contents2
+
+Changes conflicting with the upstream ones are rejected
+
+  $ echo contents_upstream > sub1/file1
+  $ git commit -a -m "change file1 upstream" 1> /dev/null
+  $ git push -q
+
+  $ cd ${TESTTMP}/sub1
+  $ echo contents_view > file1
+  $ git commit -a -m "change file1 in view" 1> /dev/null
+  $ git push -o rebase origin master:refs/for/master 2>&1 >/dev/null | sed -e 's/[ ]*$//g'
+  remote: josh-proxy
+  remote: response from upstream:
+  remote: rebase failed with conflicts in:
+  remote: sub1/file1
+  remote:
+  remote:
+  remote: error: hook declined to update refs/for/master
+  To http://localhost:8002/real_repo.git:/sub1.git
+   ! [remote rejected] master -> refs/for/master (hook declined)
+  error: failed to push some refs to 'http://localhost:8002/real_repo.git:/sub1.git'
+
+  $ bash ${TESTDIR}/destroy_test_env.sh
+  "real_repo.git" = [
+      ':/sub1',
+      ':/sub2',
+  ]
+  refs
+  |-- heads
+  |-- josh
+  |   |-- filtered
+  |   |   `-- real_repo.git
+  |   |       |-- %3A%2Fsub1
+  |   |       |   `-- heads
+  |   |       |       `-- master
+  |   |       `-- %3A%2Fsub2
+  |   |           `-- heads
+  |   |               `-- master
+  |   |-- rewrites
+  |   |   `-- real_repo.git
+  |   |       `-- * (glob)
+  |   |           `-- r_* (glob)
+  |   `-- upstream
+  |       `-- real_repo.git
+  |           `-- refs
+  |               `-- heads
+  |                   `-- master
+  |-- namespaces
+  `-- tags
+  
+  17 directories, 4 files
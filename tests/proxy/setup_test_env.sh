@@ -24,6 +24,7 @@ cp -R ${TESTDIR}/../../static/ static
 ${TESTDIR}/../../target/debug/josh-proxy\
     --port=8002\
     --graphql-root\
+    ${EXTRA_OPTS}\
     --local=${TESTTMP}/remote/scratch/\
     --remote=http://localhost:8001\
     > ${TESTTMP}/josh-proxy.out 2>&1 &
@@ -0,0 +1,81 @@
+  $ . ${TESTDIR}/setup_test_env.sh
+  $ cd ${TESTTMP}
+
+  $ git clone -q http://localhost:8001/real_repo.git
+  warning: You appear to have cloned an empty repository.
+
+  $ cd real_repo
+
+  $ mkdir sub1
+  $ echo contents1 > sub1/file1
+  $ git add sub1
+  $ git commit -m "add file1" 1> /dev/null
+  $ git push -q
+
+  $ cd ${TESTTMP}
+
+Clients showing progress get the progress of filtering as sideband messages
+
+  $ git clone --progress http://localhost:8002/real_repo.git:/sub1.git sub1 2>&1 \
+  > | tr '\r' '\n' | grep -q "^remote: josh: filtering refs: " && echo progress shown
+  progress shown
+
+Quiet clients don't
+
+  $ git clone http://localhost:8002/real_repo.git:/sub1.git sub1_quiet 2>&1 | grep -c "josh:"
+  0
+  [1]
+
+  $ cd sub1
+  $ git log --pretty=%s
+  add file1
+
+A view that was not filtered before gets filtered during the fetch of a commit,
+while its progress is streamed to the client
+
+  $ cd ${TESTTMP}/real_repo
+  $ mkdir sub2
+  $ echo contents2 > sub2/file2
+  $ git add sub2
+  $ git commit -m "add file2" 1> /dev/null
+  $ git push -q
+  $ josh-filter :/sub2 refs/heads/master 2> /dev/null
+  $ export SUB2=$(git rev-parse FILTERED_HEAD)
+
+  $ cd ${TESTTMP}
+  $ git init -q sub2
+  $ cd sub2
+  $ git fetch --no-tags --progress http://localhost:8002/real_repo.git:/sub2.git ${SUB2} 2>&1 \
+  > | tr '\r' '\n' | grep "^remote: josh: "
+  remote: josh: filtering :/sub2: 2/2 commits        
+  remote: josh: filtering refs: 1/3        
+  remote: josh: filtering refs: 2/3        
+  remote: josh: filtering refs: 3/3        
+  $ git log --pretty=%s FETCH_HEAD
+  add file2
+
+  $ bash ${TESTDIR}/destroy_test_env.sh
+  "real_repo.git" = [
+      ':/sub1',
+      ':/sub2',
+  ]
+  refs
+  |-- heads
+  |-- josh
+  |   |-- filtered
+  |   |   `-- real_repo.git
+  |   |       |-- %3A%2Fsub1
+  |   |       |   `-- heads
+  |   |       |       `-- master
+  |   |       `-- %3A%2Fsub2
+  |   |           `-- heads
+  |   |               `-- master
+  |   `-- upstream
+  |       `-- real_repo.git
+  |           `-- refs
+  |               `-- heads
+  |                   `-- master
+  |-- namespaces
+  `-- tags
+  
+  14 directories, 3 files